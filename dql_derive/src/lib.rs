@@ -2,7 +2,7 @@ use convert_case::{Case, Casing};
 use darling::{FromDeriveInput, FromField};
 use proc_macro::{self, Span, TokenStream};
 use quote::quote;
-use syn::{Data, DeriveInput, Field, Ident, Type, parse_macro_input};
+use syn::{Data, DeriveInput, Field, Ident, parse_macro_input};
 
 #[derive(Default, FromDeriveInput)]
 #[darling(default, attributes(function))]
@@ -36,6 +36,7 @@ pub fn dql_function_derive(input: TokenStream) -> TokenStream {
 
 fn dql_impl_function(ast: DeriveInput, opts: FunctionOpts) -> TokenStream {
     let function_name = opts.name_ident(&ast.ident);
+    let keyword = function_name.to_string().to_uppercase();
     let name = &ast.ident;
 
     let Data::Struct(data) = ast.data else {
@@ -55,6 +56,10 @@ fn dql_impl_function(ast: DeriveInput, opts: FunctionOpts) -> TokenStream {
         }
 
         if let Some((ident, parse_logic)) = derive_parse_field(field) {
+            let index = field_ident.len();
+            field_parse_logic.push(quote! {
+                self.must_argument(#index)?;
+            });
             field_parse_logic.push(parse_logic);
             field_ident.push(ident);
         }
@@ -62,8 +67,8 @@ fn dql_impl_function(ast: DeriveInput, opts: FunctionOpts) -> TokenStream {
 
     let impl_gen = quote! {
         impl<'a> crate::Parser<'a> {
-            fn #function_name(&self) -> crate::Result<#name> {
-                crate::parser::consume_next!(self, "#function_name")?;
+            pub(crate) fn #function_name(&self) -> crate::Result<#name> {
+                crate::parser::consume_next!(self, #keyword)?;
                 crate::parser::consume_next!(self, crate::parser::FN_OPEN)?;
                 #( #field_parse_logic )*
                 crate::parser::consume_next!(self, crate::parser::FN_CLOSE)?;
@@ -75,7 +80,15 @@ fn dql_impl_function(ast: DeriveInput, opts: FunctionOpts) -> TokenStream {
 
         impl std::fmt::Display for #name {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                write!(f, "#function_name{}{}", crate::parser::FN_OPEN, crate::parser::FN_CLOSE)
+                let args: &[&dyn std::fmt::Display] = &[ #( &self.#field_ident ),* ];
+                write!(f, "{}{}", #keyword, crate::parser::FN_OPEN)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "{} ", crate::parser::FN_SEP)?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, "{}", crate::parser::FN_CLOSE)
             }
         }
     };
@@ -0,0 +1,466 @@
+use std::{
+    ops::{Add, Div, Mul, Neg, Not, Rem, Sub},
+    time::Duration,
+};
+
+use crate::{
+    AddExpression, Aggregate, AggregateExpression, AndCondition, AvgAggregation, BoolLiteral,
+    Condition, CountAggregation, Direction, DivideExpression, EqualCondition, ExponentExpression,
+    Expr, ExpressionCondition, GreaterThanCondition, GreaterThanEqualCondition, InCondition,
+    LessThanCondition, LessThanEqualCondition, ModulusExpression, MultiplyExpression,
+    NegativeExpression, NotCondition, NotEqualCondition, NullExpression, Nulls, NumberLiteral,
+    OrCondition, OrderBy, Parameter, ParameterExpression, PathExpression, Projection, Query,
+    Segment, StringLiteral, SubCondition, SubExpression, SubtractExpression, SumAggregation,
+    ToUpper,
+    expression::{
+        PRECEDENCE_ADD, PRECEDENCE_EXPONENT, PRECEDENCE_MULTIPLY, PRECEDENCE_UNARY, precedence,
+    },
+};
+
+// QueryBuilder builds a query in code rather than parsing it. The query
+// built is the same as the one the parser returns for the same statement,
+// including the parenthesis needed to keep the order of operations:
+//
+// let query = Query::select([col("host").alias("name"), count_all().into()])
+//     .from("events")
+//     .filter(col("status").gte(500).and(col("path").ne("/health")))
+//     .group_by([col("host")])
+//     .build();
+#[derive(Debug, Clone, Default)]
+pub struct QueryBuilder {
+    query: Query,
+}
+
+impl QueryBuilder {
+    // select starts a query which selects the projections, expressions are
+    // converted into projections without an alias
+    pub fn select<P: Into<Projection>>(projections: impl IntoIterator<Item = P>) -> Self {
+        let mut builder = Self::default();
+        builder.query.select = projections.into_iter().map(Into::into).collect();
+        builder
+    }
+
+    // from adds a namespace to the `FROM` clause
+    pub fn from(mut self, namespace: impl Into<String>) -> Self {
+        self.query.from.push(namespace.into());
+        self
+    }
+
+    // filter sets the `WHERE` clause
+    pub fn filter(mut self, cond: impl Into<Condition>) -> Self {
+        self.query.filter = Some(cond.into());
+        self
+    }
+
+    // group_by sets the expressions listed in the `GROUP BY` clause
+    pub fn group_by<E: Into<Expr>>(mut self, exprs: impl IntoIterator<Item = E>) -> Self {
+        self.query.group = exprs.into_iter().map(Into::into).collect();
+        self
+    }
+
+    // having sets the `HAVING` clause
+    pub fn having(mut self, cond: impl Into<Condition>) -> Self {
+        self.query.having = Some(cond.into());
+        self
+    }
+
+    // interval sets how often a stream emits its groups
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.query.interval = Some(interval);
+        self
+    }
+
+    // evict sets how long a stream keeps a group without new documents
+    pub fn evict(mut self, evict: Duration) -> Self {
+        self.query.evict = Some(evict);
+        self
+    }
+
+    // emit_on sets the `EMIT ON` clause
+    pub fn emit_on(mut self, cond: impl Into<Condition>) -> Self {
+        self.query.emit = Some(cond.into());
+        self
+    }
+
+    // order_by sets the keys of the `ORDER BY` clause, expressions are
+    // sorted ascending
+    pub fn order_by<O: Into<OrderBy>>(mut self, order: impl IntoIterator<Item = O>) -> Self {
+        self.query.order = order.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.query.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.query.offset = Some(offset);
+        self
+    }
+
+    pub fn build(self) -> Query {
+        self.query
+    }
+}
+
+impl Query {
+    // select starts building a query in code, the same as
+    // QueryBuilder::select
+    pub fn select<P: Into<Projection>>(projections: impl IntoIterator<Item = P>) -> QueryBuilder {
+        QueryBuilder::select(projections)
+    }
+}
+
+impl From<QueryBuilder> for Query {
+    fn from(builder: QueryBuilder) -> Self {
+        builder.build()
+    }
+}
+
+// col references a key of the document, like the bare path `a`. Nested
+// paths can be built with PathExpression::new.
+pub fn col(key: impl Into<String>) -> Expr {
+    Expr::from(PathExpression::new(vec![Segment::Key(key.into())]))
+}
+
+// lit makes a literal out of a value, like `'a'`, `5` or `true`
+pub fn lit(value: impl Into<Expr>) -> Expr {
+    value.into()
+}
+
+// null is the `NULL` literal
+pub fn null() -> Expr {
+    Expr::from(NullExpression::default())
+}
+
+// param references a parameter, numbers reference positional parameters
+// like `$1` and strings reference named parameters like `:name`
+pub fn param(param: impl Into<Parameter>) -> Expr {
+    Expr::from(ParameterExpression::new(param.into()))
+}
+
+// wildcard is the `*` projection which selects the whole document
+pub fn wildcard() -> Projection {
+    Projection::Wildcard
+}
+
+pub fn sum(expr: impl Into<Expr>) -> Expr {
+    aggregate(SumAggregation::new(expr.into()))
+}
+
+pub fn avg(expr: impl Into<Expr>) -> Expr {
+    aggregate(AvgAggregation::new(expr.into()))
+}
+
+pub fn count(expr: impl Into<Expr>) -> Expr {
+    aggregate(CountAggregation::new(Some(expr.into())))
+}
+
+// count_all counts every document, `COUNT(*)`
+pub fn count_all() -> Expr {
+    aggregate(CountAggregation::new(None))
+}
+
+pub fn to_upper(expr: impl Into<Expr>) -> Expr {
+    Expr::from(ToUpper::new(expr.into()))
+}
+
+fn aggregate(aggregate: impl Into<Aggregate>) -> Expr {
+    Expr::from(AggregateExpression::new(aggregate.into()))
+}
+
+macro_rules! impl_expr_from_literal {
+    ($type:ty, $literal:ident) => {
+        impl From<$type> for Expr {
+            fn from(value: $type) -> Self {
+                Expr::from($literal::from(value))
+            }
+        }
+    };
+}
+impl_expr_from_literal!(i32, NumberLiteral);
+impl_expr_from_literal!(i64, NumberLiteral);
+impl_expr_from_literal!(u32, NumberLiteral);
+impl_expr_from_literal!(u64, NumberLiteral);
+impl_expr_from_literal!(usize, NumberLiteral);
+impl_expr_from_literal!(f64, NumberLiteral);
+impl_expr_from_literal!(bool, BoolLiteral);
+
+impl From<&str> for Expr {
+    fn from(value: &str) -> Self {
+        Expr::from(StringLiteral::new(String::from(value)))
+    }
+}
+
+impl From<String> for Expr {
+    fn from(value: String) -> Self {
+        Expr::from(StringLiteral::new(value))
+    }
+}
+
+// operand wraps an operand in parenthesis when it binds looser than the
+// operator, the right operand is also wrapped when it binds the same as
+// operators are left associative
+fn operand(expr: Expr, op_precedence: u8, right: bool) -> Expr {
+    let wrap = if right {
+        precedence(&expr) <= op_precedence
+    } else {
+        precedence(&expr) < op_precedence
+    };
+
+    if wrap {
+        Expr::from(SubExpression::new(expr))
+    } else {
+        expr
+    }
+}
+
+macro_rules! impl_expr_op {
+    ($trait:ident, $fn:ident, $name:ident, $precedence:ident) => {
+        impl<T: Into<Expr>> $trait<T> for Expr {
+            type Output = Expr;
+
+            fn $fn(self, rhs: T) -> Expr {
+                Expr::from($name::new(
+                    operand(self, $precedence, false),
+                    operand(rhs.into(), $precedence, true),
+                ))
+            }
+        }
+    };
+}
+impl_expr_op!(Add, add, AddExpression, PRECEDENCE_ADD);
+impl_expr_op!(Sub, sub, SubtractExpression, PRECEDENCE_ADD);
+impl_expr_op!(Mul, mul, MultiplyExpression, PRECEDENCE_MULTIPLY);
+impl_expr_op!(Div, div, DivideExpression, PRECEDENCE_MULTIPLY);
+impl_expr_op!(Rem, rem, ModulusExpression, PRECEDENCE_MULTIPLY);
+
+impl Neg for Expr {
+    type Output = Expr;
+
+    fn neg(self) -> Expr {
+        Expr::from(NegativeExpression::new(operand(
+            self,
+            PRECEDENCE_UNARY,
+            true,
+        )))
+    }
+}
+
+impl Expr {
+    // pow raises the expression to the power of exp, `a ^ exp`. Since ^ is
+    // right associative the sides are wrapped the other way around to the
+    // other operators, and exp can be a unary expression like `a ^ -b`.
+    pub fn pow(self, exp: impl Into<Expr>) -> Expr {
+        Expr::from(ExponentExpression::new(
+            operand(self, PRECEDENCE_EXPONENT, true),
+            operand(exp.into(), PRECEDENCE_UNARY, false),
+        ))
+    }
+
+    pub fn eq(self, rhs: impl Into<Expr>) -> Condition {
+        Condition::from(EqualCondition::new(self, rhs.into()))
+    }
+
+    pub fn ne(self, rhs: impl Into<Expr>) -> Condition {
+        Condition::from(NotEqualCondition::new(self, rhs.into()))
+    }
+
+    pub fn gt(self, rhs: impl Into<Expr>) -> Condition {
+        Condition::from(GreaterThanCondition::new(self, rhs.into()))
+    }
+
+    pub fn gte(self, rhs: impl Into<Expr>) -> Condition {
+        Condition::from(GreaterThanEqualCondition::new(self, rhs.into()))
+    }
+
+    pub fn lt(self, rhs: impl Into<Expr>) -> Condition {
+        Condition::from(LessThanCondition::new(self, rhs.into()))
+    }
+
+    pub fn lte(self, rhs: impl Into<Expr>) -> Condition {
+        Condition::from(LessThanEqualCondition::new(self, rhs.into()))
+    }
+
+    // is_in is true when the expression is within the list, or is a key
+    // of the map, `a IN rhs`
+    pub fn is_in(self, rhs: impl Into<Expr>) -> Condition {
+        Condition::from(InCondition::new(self, rhs.into()))
+    }
+
+    // alias names the projection, `expr AS alias`
+    pub fn alias(self, alias: impl Into<String>) -> Projection {
+        Projection::Expr {
+            expr: self,
+            alias: Some(alias.into()),
+        }
+    }
+
+    pub fn asc(self) -> OrderBy {
+        OrderBy::new(self, Direction::Asc, None)
+    }
+
+    pub fn desc(self) -> OrderBy {
+        OrderBy::new(self, Direction::Desc, None)
+    }
+}
+
+impl From<Expr> for Projection {
+    fn from(expr: Expr) -> Self {
+        Projection::Expr { expr, alias: None }
+    }
+}
+
+impl From<Expr> for OrderBy {
+    fn from(expr: Expr) -> Self {
+        expr.asc()
+    }
+}
+
+impl OrderBy {
+    pub fn nulls_first(self) -> Self {
+        OrderBy::new(self.expr().clone(), self.direction(), Some(Nulls::First))
+    }
+
+    pub fn nulls_last(self) -> Self {
+        OrderBy::new(self.expr().clone(), self.direction(), Some(Nulls::Last))
+    }
+}
+
+// Precedence of the logical operators, NOT and comparisons bind tightest
+const PRECEDENCE_OR: u8 = 1;
+const PRECEDENCE_AND: u8 = 2;
+const PRECEDENCE_NOT: u8 = 3;
+
+fn condition_precedence(cond: &Condition) -> u8 {
+    match cond {
+        Condition::OrCondition(_) => PRECEDENCE_OR,
+        Condition::AndCondition(_) => PRECEDENCE_AND,
+        _ => PRECEDENCE_NOT,
+    }
+}
+
+// condition_operand wraps a condition in parenthesis following the same
+// rules as operand
+fn condition_operand(cond: Condition, op_precedence: u8, right: bool) -> Condition {
+    let wrap = if right {
+        condition_precedence(&cond) <= op_precedence
+    } else {
+        condition_precedence(&cond) < op_precedence
+    };
+
+    if wrap {
+        Condition::from(SubCondition::new(cond))
+    } else {
+        cond
+    }
+}
+
+impl Condition {
+    pub fn and(self, rhs: impl Into<Condition>) -> Condition {
+        Condition::from(AndCondition::new(
+            condition_operand(self, PRECEDENCE_AND, false),
+            condition_operand(rhs.into(), PRECEDENCE_AND, true),
+        ))
+    }
+
+    pub fn or(self, rhs: impl Into<Condition>) -> Condition {
+        Condition::from(OrCondition::new(
+            condition_operand(self, PRECEDENCE_OR, false),
+            condition_operand(rhs.into(), PRECEDENCE_OR, true),
+        ))
+    }
+}
+
+impl Not for Condition {
+    type Output = Condition;
+
+    fn not(self) -> Condition {
+        Condition::from(NotCondition::new(condition_operand(
+            self,
+            PRECEDENCE_NOT,
+            false,
+        )))
+    }
+}
+
+// Expressions are used as conditions like `WHERE active`
+impl From<Expr> for Condition {
+    fn from(expr: Expr) -> Self {
+        Condition::from(ExpressionCondition::new(expr))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Parser, Result};
+
+    macro_rules! assert_builder {
+        ( $builder:expr, $query:expr ) => {
+            let built = $builder.build();
+            let parsed = Parser::from($query).query()?;
+            assert_eq!(format!("{:?}", built), format!("{:?}", parsed));
+            assert_eq!(built.to_string(), parsed.to_string());
+        };
+    }
+
+    #[test]
+    fn test_builder() -> Result<()> {
+        assert_builder!(
+            QueryBuilder::select([wildcard()]).from("events"),
+            "SELECT * FROM events"
+        );
+        assert_builder!(
+            Query::select([col("host").alias("name"), count_all().into()])
+                .from("events")
+                .from("other ns")
+                .filter(col("status").gte(500).and(col("path").ne("/health")))
+                .group_by([col("host")])
+                .having(count_all().gt(10))
+                .order_by([count_all().desc().nulls_last(), col("host").into()])
+                .limit(10)
+                .offset(5),
+            "SELECT host AS name, COUNT(*) FROM events, \"other ns\" \
+             WHERE status >= 500 AND path != '/health' GROUP BY host \
+             HAVING COUNT(*) > 10 ORDER BY COUNT(*) DESC NULLS LAST, host LIMIT 10 OFFSET 5"
+        );
+        assert_builder!(
+            QueryBuilder::select([(col("a") + 1) * 2, col("a") - (lit(1) - 2), col("b").pow(2)])
+                .from("events")
+                .filter((!col("a").eq(1)).or(col("b").is_in(param("names")).and(col("c"))))
+                .order_by([to_upper(col("d"))]),
+            "SELECT (a + 1) * 2, a - (1 - 2), b ^ 2 FROM events \
+             WHERE NOT a = 1 OR b IN :names AND c ORDER BY TO_UPPER(d)"
+        );
+        assert_builder!(
+            QueryBuilder::select([sum(col("a")) / count(col("a")), avg(lit(1.5))])
+                .from("events")
+                .filter((!col("a").eq(null()).or(col("b").lt(param(1)))).and(lit(true)))
+                .group_by([col("a")])
+                .interval(Duration::from_secs(60))
+                .evict(Duration::from_millis(1500))
+                .emit_on(col("a").lte("x")),
+            "SELECT SUM(a) / COUNT(a), AVG(1.5) FROM events \
+             WHERE NOT (a = NULL OR b < $1) AND TRUE GROUP BY a \
+             INTERVAL '60s' EVICT '1500ms' EMIT ON a <= 'x'"
+        );
+        assert_builder!(
+            QueryBuilder::select([-(col("a") + 1), (-col("b")).pow(2), col("c") * -lit(2)])
+                .from("events"),
+            "SELECT -(a + 1), (-b) ^ 2, c * -2 FROM events"
+        );
+        assert_builder!(
+            QueryBuilder::select([
+                col("a").pow(col("b").pow(2)),
+                col("a").pow(2).pow(3),
+                col("a").pow(-lit(1)),
+            ])
+            .from("events"),
+            "SELECT a ^ b ^ 2, (a ^ 2) ^ 3, a ^ -1 FROM events"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use crate::{
+    Any, Container, Expr, Result,
+    expression::leads_with_not,
+    parser::{
+        EQUAL, GREATER_THAN, GREATER_THAN_EQUAL, IN, LESS_THAN, LESS_THAN_EQUAL, NOT_EQUAL,
+        SUB_EXPR_CLOSE, SUB_EXPR_OPEN,
+    },
+};
+
+use super::Conditional;
+
+// Comparison conditions, these evaluate both sides and compare them with
+// the PartialEq and PartialOrd implementations on Any. Values that can not
+// be compared, like a string and a number, are never greater or less than
+// each other.
+macro_rules! impl_condition_compare_op {
+    ($name:ident, $op:tt, $token:ident) => {
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        pub struct $name {
+            left: Expr,
+            right: Expr,
+        }
+
+        impl $name {
+            pub fn new(left: Expr, right: Expr) -> Self {
+                Self { left, right }
+            }
+        }
+
+        impl Conditional for $name {
+            fn evaluate<T: Container>(&self, c: &T) -> Result<bool> {
+                let left = self.left.evaluate(c)?;
+                let right = self.right.evaluate(c)?;
+
+                Ok(left $op right)
+            }
+
+            fn expressions(&self) -> Vec<&Expr> {
+                vec![&self.left, &self.right]
+            }
+
+            fn expressions_mut(&mut self) -> Vec<&mut Expr> {
+                vec![&mut self.left, &mut self.right]
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write_comparison(f, &self.left, $token, &self.right)
+            }
+        }
+    };
+}
+
+// write_comparison writes a comparison, wrapping the left expression in
+// parenthesis when it starts with NOT so it isn't read as a NOT condition
+fn write_comparison(
+    f: &mut std::fmt::Formatter<'_>,
+    left: &Expr,
+    op: &str,
+    right: &Expr,
+) -> std::fmt::Result {
+    if leads_with_not(left) {
+        write!(f, "{}{}{}", SUB_EXPR_OPEN, left, SUB_EXPR_CLOSE)?;
+    } else {
+        write!(f, "{}", left)?;
+    }
+
+    write!(f, " {} {}", op, right)
+}
+
+impl_condition_compare_op!(EqualCondition, ==, EQUAL);
+impl_condition_compare_op!(NotEqualCondition, !=, NOT_EQUAL);
+impl_condition_compare_op!(GreaterThanCondition, >, GREATER_THAN);
+impl_condition_compare_op!(GreaterThanEqualCondition, >=, GREATER_THAN_EQUAL);
+impl_condition_compare_op!(LessThanCondition, <, LESS_THAN);
+impl_condition_compare_op!(LessThanEqualCondition, <=, LESS_THAN_EQUAL);
+
+// InCondition checks if the left value is within the right value. The right
+// value can be a list, in which case the items are checked, or a map, in
+// which case the keys are checked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InCondition {
+    left: Expr,
+    right: Expr,
+}
+
+impl InCondition {
+    pub fn new(left: Expr, right: Expr) -> Self {
+        Self { left, right }
+    }
+}
+
+impl Conditional for InCondition {
+    fn evaluate<T: Container>(&self, c: &T) -> Result<bool> {
+        let left = self.left.evaluate(c)?;
+        let right = self.right.evaluate(c)?;
+
+        Ok(match (&left, &right) {
+            (_, Any::List(list)) => list.contains(&left),
+            (Any::Str(key), Any::Map(map)) => map.contains_key(key),
+            _ => false,
+        })
+    }
+
+    fn expressions(&self) -> Vec<&Expr> {
+        vec![&self.left, &self.right]
+    }
+
+    fn expressions_mut(&mut self) -> Vec<&mut Expr> {
+        vec![&mut self.left, &mut self.right]
+    }
+}
+
+impl Display for InCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_comparison(f, &self.left, IN, &self.right)
+    }
+}
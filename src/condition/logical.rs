@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use crate::{
+    Container, Expr, Result,
+    parser::{AND, NOT, OR, SUB_CONDITION, SUB_CONDITION_END},
+};
+
+use super::{Condition, Conditional, must_bool};
+
+// Precedence of the logical conditions, every other condition is an operand
+pub(crate) const PRECEDENCE_OR: u8 = 1;
+pub(crate) const PRECEDENCE_AND: u8 = 2;
+const PRECEDENCE_NOT: u8 = 3;
+const PRECEDENCE_OPERAND: u8 = 4;
+
+pub(crate) fn precedence(cond: &Condition) -> u8 {
+    match cond {
+        Condition::OrCondition(_) => PRECEDENCE_OR,
+        Condition::AndCondition(_) => PRECEDENCE_AND,
+        Condition::NotCondition(_) => PRECEDENCE_NOT,
+        _ => PRECEDENCE_OPERAND,
+    }
+}
+
+// write_logical writes AND and OR, wrapping either side in parenthesis when
+// it would otherwise be parsed into a different tree. Like math operations
+// they are left associative, so the right side is also wrapped when it has
+// the same precedence, like `a AND (b AND c)`.
+fn write_logical(
+    f: &mut std::fmt::Formatter<'_>,
+    left: &Condition,
+    op: &str,
+    right: &Condition,
+    op_precedence: u8,
+) -> std::fmt::Result {
+    write_operand(f, left, precedence(left) < op_precedence)?;
+    write!(f, " {} ", op)?;
+    write_operand(f, right, precedence(right) <= op_precedence)
+}
+
+pub(crate) fn write_operand(
+    f: &mut std::fmt::Formatter<'_>,
+    cond: &Condition,
+    wrap: bool,
+) -> std::fmt::Result {
+    if wrap {
+        write!(f, "{}{}{}", SUB_CONDITION, cond, SUB_CONDITION_END)
+    } else {
+        write!(f, "{}", cond)
+    }
+}
+
+// AndCondition is true when both sides are true, the right side is only
+// evaluated when the left side is true.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AndCondition {
+    left: Box<Condition>,
+    right: Box<Condition>,
+}
+
+impl AndCondition {
+    pub fn new(left: Condition, right: Condition) -> Self {
+        Self {
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+}
+
+impl Conditional for AndCondition {
+    fn evaluate<T: Container>(&self, c: &T) -> Result<bool> {
+        Ok(self.left.evaluate(c)? && self.right.evaluate(c)?)
+    }
+
+    fn conditions(&self) -> Vec<&Condition> {
+        vec![&self.left, &self.right]
+    }
+
+    fn conditions_mut(&mut self) -> Vec<&mut Condition> {
+        vec![&mut self.left, &mut self.right]
+    }
+}
+
+impl Display for AndCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_logical(f, &self.left, AND, &self.right, PRECEDENCE_AND)
+    }
+}
+
+// OrCondition is true when either side is true, the right side is only
+// evaluated when the left side is false.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrCondition {
+    left: Box<Condition>,
+    right: Box<Condition>,
+}
+
+impl OrCondition {
+    pub fn new(left: Condition, right: Condition) -> Self {
+        Self {
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+}
+
+impl Conditional for OrCondition {
+    fn evaluate<T: Container>(&self, c: &T) -> Result<bool> {
+        Ok(self.left.evaluate(c)? || self.right.evaluate(c)?)
+    }
+
+    fn conditions(&self) -> Vec<&Condition> {
+        vec![&self.left, &self.right]
+    }
+
+    fn conditions_mut(&mut self) -> Vec<&mut Condition> {
+        vec![&mut self.left, &mut self.right]
+    }
+}
+
+impl Display for OrCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_logical(f, &self.left, OR, &self.right, PRECEDENCE_OR)
+    }
+}
+
+// NotCondition negates the condition it wraps
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotCondition {
+    cond: Box<Condition>,
+}
+
+impl NotCondition {
+    pub fn new(cond: Condition) -> Self {
+        Self {
+            cond: Box::new(cond),
+        }
+    }
+}
+
+impl Conditional for NotCondition {
+    fn evaluate<T: Container>(&self, c: &T) -> Result<bool> {
+        Ok(!self.cond.evaluate(c)?)
+    }
+
+    fn conditions(&self) -> Vec<&Condition> {
+        vec![&self.cond]
+    }
+
+    fn conditions_mut(&mut self) -> Vec<&mut Condition> {
+        vec![&mut self.cond]
+    }
+}
+
+impl Display for NotCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ", NOT)?;
+        write_operand(f, &self.cond, precedence(&self.cond) < PRECEDENCE_NOT)
+    }
+}
+
+// SubCondition is a condition wrapped in parenthesis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubCondition {
+    cond: Box<Condition>,
+}
+
+impl SubCondition {
+    pub fn new(cond: Condition) -> Self {
+        Self {
+            cond: Box::new(cond),
+        }
+    }
+}
+
+impl Conditional for SubCondition {
+    fn evaluate<T: Container>(&self, c: &T) -> Result<bool> {
+        self.cond.evaluate(c)
+    }
+
+    fn conditions(&self) -> Vec<&Condition> {
+        vec![&self.cond]
+    }
+
+    fn conditions_mut(&mut self) -> Vec<&mut Condition> {
+        vec![&mut self.cond]
+    }
+}
+
+impl Display for SubCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}{}", SUB_CONDITION, self.cond, SUB_CONDITION_END)
+    }
+}
+
+// ExpressionCondition uses the result of an expression as the condition,
+// for instance `WHERE active`. The expression must return a bool or null.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpressionCondition {
+    expr: Expr,
+}
+
+impl ExpressionCondition {
+    pub fn new(expr: Expr) -> Self {
+        Self { expr }
+    }
+}
+
+impl Conditional for ExpressionCondition {
+    fn evaluate<T: Container>(&self, c: &T) -> Result<bool> {
+        must_bool(&self.expr.evaluate(c)?)
+    }
+
+    fn expressions(&self) -> Vec<&Expr> {
+        vec![&self.expr]
+    }
+
+    fn expressions_mut(&mut self) -> Vec<&mut Expr> {
+        vec![&mut self.expr]
+    }
+}
+
+impl Display for ExpressionCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.expr)
+    }
+}
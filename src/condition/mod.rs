@@ -0,0 +1,421 @@
+mod compare;
+mod logical;
+
+pub use compare::*;
+pub use logical::*;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Display};
+
+use crate::{
+    AggregateExpression, Container, Error, Expr, Parser, TokenKind,
+    error::Result,
+    parser::{
+        AND, EQUAL, EQUAL_DOUBLE, GREATER_THAN, GREATER_THAN_EQUAL, IN, LESS_THAN, LESS_THAN_EQUAL,
+        NEGATE, NOT, NOT_EQUAL, OR, SUB_CONDITION, SUB_CONDITION_END, consume, consume_next,
+        continue_if, is_next,
+    },
+};
+
+// Conditional is a trait that takes in a container and decides if the
+// container matches or not. Conditions are used by the `WHERE` and
+// `HAVING` clauses.
+pub trait Conditional: Display + Debug + Send + Sync + Clone {
+    fn evaluate<T: Container>(&self, c: &T) -> Result<bool>;
+
+    // conditions returns the conditions nested directly within this
+    // condition
+    fn conditions(&self) -> Vec<&Condition> {
+        Vec::new()
+    }
+
+    fn conditions_mut(&mut self) -> Vec<&mut Condition> {
+        Vec::new()
+    }
+
+    // expressions returns the expressions used directly by this condition
+    fn expressions(&self) -> Vec<&Expr> {
+        Vec::new()
+    }
+
+    fn expressions_mut(&mut self) -> Vec<&mut Expr> {
+        Vec::new()
+    }
+}
+
+macro_rules! condition_impl {
+    ($( $i:ident ),* ) => {
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        #[serde(tag = "type", rename_all = "snake_case")]
+        pub enum Condition {
+            $( $i($i), )*
+        }
+
+        impl Condition {
+            pub fn evaluate<T: Container>(&self, c: &T) -> Result<bool> {
+                match self {
+                    $( Condition::$i(cond) => cond.evaluate(c), )*
+                }
+            }
+
+            pub fn conditions(&self) -> Vec<&Condition> {
+                match self {
+                    $( Condition::$i(cond) => cond.conditions(), )*
+                }
+            }
+
+            pub fn expressions(&self) -> Vec<&Expr> {
+                match self {
+                    $( Condition::$i(cond) => cond.expressions(), )*
+                }
+            }
+
+            pub fn conditions_mut(&mut self) -> Vec<&mut Condition> {
+                match self {
+                    $( Condition::$i(cond) => cond.conditions_mut(), )*
+                }
+            }
+
+            pub fn expressions_mut(&mut self) -> Vec<&mut Expr> {
+                match self {
+                    $( Condition::$i(cond) => cond.expressions_mut(), )*
+                }
+            }
+        }
+
+        impl Display for Condition {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $( Condition::$i(cond) => Display::fmt(cond, f), )*
+                }
+            }
+        }
+
+        $(
+            impl From<$i> for Condition {
+                fn from(val: $i) -> Self {
+                    Condition::$i(val)
+                }
+            }
+        )*
+    };
+}
+
+condition_impl!(
+    AndCondition,
+    OrCondition,
+    NotCondition,
+    SubCondition,
+    ExpressionCondition,
+    EqualCondition,
+    NotEqualCondition,
+    GreaterThanCondition,
+    GreaterThanEqualCondition,
+    LessThanCondition,
+    LessThanEqualCondition,
+    InCondition
+);
+
+impl Condition {
+    // aggregates returns every aggregate used within the condition
+    pub fn aggregates(&self) -> Vec<&AggregateExpression> {
+        let mut aggregates = Vec::new();
+        let mut stack = vec![self];
+
+        while let Some(cond) = stack.pop() {
+            for expr in cond.expressions() {
+                aggregates.extend(expr.aggregates());
+            }
+            stack.extend(cond.conditions().into_iter().rev());
+        }
+
+        aggregates
+    }
+}
+
+impl<'a> TryFrom<&Parser<'a>> for Condition {
+    type Error = crate::Error;
+    fn try_from(value: &Parser<'a>) -> std::result::Result<Self, Self::Error> {
+        value.condition()
+    }
+}
+
+impl<'a> Parser<'a> {
+    // condition parses a condition. Conditions follow the order of
+    // operations NOT, AND and then OR, parenthesis can be used to group
+    // conditions together.
+    pub fn condition(&self) -> Result<Condition> {
+        self.nested(|| self.parse_condition_or())
+    }
+
+    // parse_condition_or handles OR linearly, passing lower scopes into
+    // the and function
+    fn parse_condition_or(&self) -> Result<Condition> {
+        self.chain(|link| {
+            let mut cond = self.parse_condition_and()?;
+
+            while continue_if!(self, OR) {
+                link()?;
+                let right = self.parse_condition_and()?;
+                cond = Condition::from(OrCondition::new(cond, right));
+            }
+
+            Ok(cond)
+        })
+    }
+
+    // parse_condition_and handles AND linearly, passing lower scopes into
+    // the not function
+    fn parse_condition_and(&self) -> Result<Condition> {
+        self.chain(|link| {
+            let mut cond = self.parse_condition_not()?;
+
+            while continue_if!(self, AND) {
+                link()?;
+                let right = self.parse_condition_not()?;
+                cond = Condition::from(AndCondition::new(cond, right));
+            }
+
+            Ok(cond)
+        })
+    }
+
+    // parse_condition_not handles negating the condition that follows it
+    fn parse_condition_not(&self) -> Result<Condition> {
+        if continue_if!(self, NEGATE) || continue_if!(self, NOT) {
+            let cond = self.nested(|| self.parse_condition_not())?;
+            return Ok(Condition::from(NotCondition::new(cond)));
+        }
+
+        self.parse_condition_group()
+    }
+
+    // parse_condition_group handles conditions wrapped in parenthesis. Since
+    // expressions can also be wrapped in parenthesis, like `(a + b) > 5`, we
+    // first try to parse a comparison and fall back to a sub condition.
+    fn parse_condition_group(&self) -> Result<Condition> {
+        if !is_next!(self, SUB_CONDITION) {
+            return self.parse_comparison();
+        }
+
+        let checkpoint = self.checkpoint();
+        if let Ok(cond) = self.parse_comparison() {
+            return Ok(cond);
+        }
+        self.restore(checkpoint);
+
+        consume!(self);
+        let cond = self.condition()?;
+        consume_next!(self, SUB_CONDITION_END)?;
+
+        Ok(Condition::from(SubCondition::new(cond)))
+    }
+
+    // parse_comparison parses an expression and the comparison that
+    // follows it. When there is no comparison the expression is used as
+    // the condition itself.
+    fn parse_comparison(&self) -> Result<Condition> {
+        let left = self.expression()?;
+
+        let op = self
+            .peak()
+            .filter(|v| matches!(v.kind(), TokenKind::Operator | TokenKind::Keyword))
+            .map(|v| v.text().to_uppercase())
+            .unwrap_or_default();
+        let cond = match op.as_str() {
+            EQUAL_DOUBLE if !self.double_equal() => {
+                consume!(self);
+                return Err(Error::with_history(
+                    &format!("unexpected \"{}\"", EQUAL_DOUBLE),
+                    self.history(),
+                )
+                .hint(&format!("use {} to compare values", EQUAL)));
+            }
+            EQUAL | EQUAL_DOUBLE => {
+                consume!(self);
+                Condition::from(EqualCondition::new(left, self.expression()?))
+            }
+            NOT_EQUAL => {
+                consume!(self);
+                Condition::from(NotEqualCondition::new(left, self.expression()?))
+            }
+            GREATER_THAN => {
+                consume!(self);
+                Condition::from(GreaterThanCondition::new(left, self.expression()?))
+            }
+            GREATER_THAN_EQUAL => {
+                consume!(self);
+                Condition::from(GreaterThanEqualCondition::new(left, self.expression()?))
+            }
+            LESS_THAN => {
+                consume!(self);
+                Condition::from(LessThanCondition::new(left, self.expression()?))
+            }
+            LESS_THAN_EQUAL => {
+                consume!(self);
+                Condition::from(LessThanEqualCondition::new(left, self.expression()?))
+            }
+            IN => {
+                consume!(self);
+                Condition::from(InCondition::new(left, self.expression()?))
+            }
+            _ => Condition::from(ExpressionCondition::new(left)),
+        };
+
+        Ok(cond)
+    }
+}
+
+// must_bool is used when a value is used as a condition. Only bools and
+// null (which is false) can be used this way.
+pub(crate) fn must_bool(value: &crate::Any<'_>) -> Result<bool> {
+    match value {
+        crate::Any::Bool(b) => Ok(*b),
+        crate::Any::Null => Ok(false),
+        value => Err(Error::type_mismatch(crate::Kind::Bool, value.kind())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Any, parser::Parser};
+
+    macro_rules! assert_condition {
+        ( $source:expr, $cond:expr, $expected:expr) => {
+            let parser = Parser::from($cond);
+            let cond = parser.condition()?;
+            assert!(parser.peak().is_none(), "{} was not fully parsed", $cond);
+            let d: Any = serde_json::from_str($source).unwrap();
+            assert_eq!(cond.evaluate(&d)?, $expected, "{}", $cond);
+        };
+    }
+
+    #[test]
+    fn test_condition() -> Result<()> {
+        let doc = r#"{"a": 5, "b": "hello", "c": [1, 2, 3], "d": true, "e": null}"#;
+
+        assert_condition!(doc, "a = 5", true);
+        assert_condition!(doc, "a == 5.0", true);
+        assert_condition!(doc, "a != 5", false);
+        assert_condition!(doc, "a > 4", true);
+        assert_condition!(doc, "a >= 5", true);
+        assert_condition!(doc, "a < 5", false);
+        assert_condition!(doc, "a <= 5", true);
+        assert_condition!(doc, "b = 'hello'", true);
+        assert_condition!(doc, "b > 'abc'", true);
+        assert_condition!(doc, "b > 5", false);
+        assert_condition!(doc, "b < 5", false);
+        assert_condition!(doc, "a IN [1, 5]", true);
+        assert_condition!(doc, "a in c", false);
+        assert_condition!(doc, "2 IN c", true);
+        assert_condition!(doc, "'b' IN {'b': 1}", true);
+        assert_condition!(doc, "d", true);
+        assert_condition!(doc, "e", false);
+        assert_condition!(doc, "missing = NULL", true);
+        assert_condition!(doc, "e = NULL", true);
+        assert_condition!(doc, "a + 1 = 6", true);
+        assert_condition!(doc, "(a + 1) * 2 = 12", true);
+        assert_condition!(doc, "(a) = 5", true);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_condition_precedence() -> Result<()> {
+        let doc = r#"{"a": 5, "b": "hello"}"#;
+
+        assert_condition!(doc, "a = 5 AND b = 'hello'", true);
+        assert_condition!(doc, "a = 5 and b = 'bye'", false);
+        assert_condition!(doc, "a = 4 OR b = 'hello'", true);
+        assert_condition!(doc, "a = 4 or b = 'bye'", false);
+        assert_condition!(doc, "!a = 5", false);
+        assert_condition!(doc, "NOT a = 4", true);
+        assert_condition!(doc, "NOT NOT a = 5", true);
+
+        // AND binds tighter than OR
+        assert_condition!(doc, "a = 5 OR a = 4 AND b = 'bye'", true);
+        assert_condition!(doc, "(a = 5 OR a = 4) AND b = 'bye'", false);
+
+        // NOT binds tighter than AND
+        assert_condition!(doc, "NOT a = 4 AND b = 'bye'", false);
+        assert_condition!(doc, "NOT (a = 4 AND b = 'bye')", true);
+        assert_condition!(doc, "!(a = 5 OR a = 4) OR ((b = 'hello'))", true);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_condition_display() -> Result<()> {
+        let parser = Parser::from("NOT (a = 'x' AND b != 'bye') OR c IN d AND d >= e");
+        assert_eq!(
+            parser.condition()?.to_string(),
+            "NOT (`a` = 'x' AND `b` != 'bye') OR `c` IN `d` AND `d` >= `e`"
+        );
+
+        // a NOT expression at the start of a comparison is wrapped so it
+        // isn't read back as a NOT condition
+        let not = Parser::from("NOT a + 1").expression()?;
+        let cond = Condition::from(EqualCondition::new(not, Parser::from("b").expression()?));
+        assert_eq!(cond.to_string(), "(NOT `a` + 1) = `b`");
+        let cond = Parser::from(cond.to_string().as_str()).condition()?;
+        assert!(matches!(cond, Condition::EqualCondition(_)));
+        assert_eq!(
+            Parser::from("a IN [NOT b, -c]").condition()?.to_string(),
+            "`a` IN [NOT `b`, -`c`]"
+        );
+
+        // trees built without SubCondition are wrapped where they need to be
+        let cond = |source: &str| Parser::from(source).condition();
+        let built = [
+            (
+                Condition::from(AndCondition::new(
+                    Condition::from(OrCondition::new(cond("a")?, cond("b")?)),
+                    cond("c")?,
+                )),
+                "(`a` OR `b`) AND `c`",
+            ),
+            (
+                Condition::from(OrCondition::new(
+                    cond("a")?,
+                    Condition::from(OrCondition::new(cond("b")?, cond("c")?)),
+                )),
+                "`a` OR (`b` OR `c`)",
+            ),
+            (
+                Condition::from(NotCondition::new(Condition::from(AndCondition::new(
+                    cond("a")?,
+                    cond("b")?,
+                )))),
+                "NOT (`a` AND `b`)",
+            ),
+            (
+                Condition::from(AndCondition::new(
+                    Condition::from(NotCondition::new(cond("a")?)),
+                    cond("b = 1 OR c")?,
+                )),
+                "NOT `a` AND (`b` = 1 OR `c`)",
+            ),
+        ];
+        for (built, expected) in built {
+            assert_eq!(built.to_string(), expected);
+            assert_eq!(cond(expected)?.to_string(), expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_condition_errors() {
+        let d = Any::Null;
+        assert!(Parser::from("(a = 5").condition().is_err());
+        assert!(Parser::from("a = ").condition().is_err());
+        assert!(Parser::from("a AND").condition().is_err());
+        assert!(
+            Parser::from("'a'")
+                .condition()
+                .unwrap()
+                .evaluate(&d)
+                .is_err()
+        );
+    }
+}
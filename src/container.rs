@@ -1,3 +1,108 @@
 use std::fmt::{Debug, Display};
 
-pub trait Container: Debug + Display + Sync + Send {}
+use crate::{Any, Kind, Parameter, Str};
+
+// Container is the data a query is evaluated against. It gives expressions
+// access to the values held within as Any values, which can borrow strings
+// and bytes from the underlying document model but own their lists and maps.
+//
+// Only value is required, every other method is implemented on top of it.
+// The defaults convert the whole document with value on every call, even to
+// read a single child, so document models that can look up children more
+// cheaply should override the other methods as well. Any does, its lookups
+// only rebuild the lists and maps within the child returned.
+pub trait Container: Debug + Display + Sync + Send {
+    // value returns the entire container as an Any
+    fn value(&self) -> Any<'_>;
+
+    // kind returns the type of the value held by the container
+    fn kind(&self) -> Kind {
+        self.value().kind()
+    }
+
+    // len returns the number of direct children, which is 0 for anything
+    // other than a list or map
+    fn len(&self) -> usize {
+        self.children().count()
+    }
+
+    // is_empty returns true when the container has no children
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // keys returns the keys of the container, when the container is a map
+    fn keys(&self) -> Box<dyn Iterator<Item = Str<'_>> + '_> {
+        match self.value() {
+            Any::Map(map) => Box::new(map.into_keys()),
+            _ => Box::new(std::iter::empty()),
+        }
+    }
+
+    // get returns the child stored under key, when the container is a map
+    fn get(&self, key: &str) -> Option<Any<'_>> {
+        self.value().take_key(key)
+    }
+
+    // index returns the child stored at index, when the container is a list
+    fn index(&self, index: usize) -> Option<Any<'_>> {
+        self.value().take_index(index)
+    }
+
+    // children returns all the direct children of the container. For lists
+    // this is each item, for maps this is each value.
+    fn children(&self) -> Box<dyn Iterator<Item = Any<'_>> + '_> {
+        Box::new(self.value().into_children().into_iter())
+    }
+
+    // aggregate returns the result of the named aggregation, like
+    // `COUNT(*)`. Only containers which represent a group of documents
+    // have aggregates.
+    fn aggregate(&self, _name: &str) -> Option<Any<'_>> {
+        None
+    }
+
+    // parameter returns the value bound to a parameter of the query, see
+    // Params for binding values to a container
+    fn parameter(&self, _param: &Parameter) -> Option<Any<'_>> {
+        None
+    }
+}
+
+impl<T: Container> Container for &T {
+    fn value(&self) -> Any<'_> {
+        (*self).value()
+    }
+
+    fn kind(&self) -> Kind {
+        (*self).kind()
+    }
+
+    fn len(&self) -> usize {
+        (*self).len()
+    }
+
+    fn keys(&self) -> Box<dyn Iterator<Item = Str<'_>> + '_> {
+        (*self).keys()
+    }
+
+    fn get(&self, key: &str) -> Option<Any<'_>> {
+        (*self).get(key)
+    }
+
+    fn index(&self, index: usize) -> Option<Any<'_>> {
+        (*self).index(index)
+    }
+
+    fn children(&self) -> Box<dyn Iterator<Item = Any<'_>> + '_> {
+        (*self).children()
+    }
+
+    fn aggregate(&self, name: &str) -> Option<Any<'_>> {
+        (*self).aggregate(name)
+    }
+
+    fn parameter(&self, param: &Parameter) -> Option<Any<'_>> {
+        (*self).parameter(param)
+    }
+}
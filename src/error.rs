@@ -1,43 +1,264 @@
-use std::fmt::Display;
+use std::{fmt::Display, ops::Range};
+
+use crate::{Kind, Parameter, Span};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 // Error is the Error type for DQL
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Error {
-    InvalidType,
+    // TypeMismatch is returned when a value is not the kind of value an
+    // operation expects, like adding a string to a number
+    TypeMismatch {
+        expected: Kind,
+        found: Kind,
+    },
+    // UnknownFunction is returned when a query calls a function which does
+    // not exist
+    UnknownFunction {
+        name: String,
+        span: Span,
+    },
+    // Parse is returned when a query can not be parsed. The span points at
+    // the part of the query which could not be parsed, and the hint, when
+    // there is one, suggests how to fix it.
+    Parse {
+        message: String,
+        span: Span,
+        hint: Option<String>,
+    },
+    // UnboundParameter is returned when a query uses a parameter which no
+    // value was bound to
+    UnboundParameter {
+        parameter: Parameter,
+    },
+    // UnexpectedEOF is returned when the query ends before it is complete
+    UnexpectedEOF {
+        span: Span,
+    },
+    // LimitExceeded is returned when a query is larger than the parser
+    // allows, like being nested too deeply. See ParserBuilder for the limits.
+    LimitExceeded {
+        message: String,
+        span: Span,
+    },
+    // Arithmetic is returned when math can not be done, like dividing by
+    // zero or overflowing an integer
+    Arithmetic(String),
     InvalidQuery(String),
     ExpressionError(String),
-    UnexpectedEOF,
 }
 
 impl Error {
     pub fn with_history(msg: &str, history: History<'_>) -> Self {
-        if history.1.is_empty() {
-            Error::InvalidQuery(format!("[ {} ] {}", history.0, msg))
-        } else {
-            Error::InvalidQuery(format!("[ {} █ {} ]: {}", history.0, history.1, msg))
+        Error::Parse {
+            message: String::from(msg),
+            span: history.span(),
+            hint: None,
         }
     }
 
     pub fn unexpected_eof(history: History<'_>) -> Self {
-        Error::InvalidQuery(format!("unexpected EOF at: \"{}\"", history))
+        Error::UnexpectedEOF {
+            span: history.end(),
+        }
+    }
+
+    pub fn unknown_function(name: &str, history: History<'_>) -> Self {
+        Error::UnknownFunction {
+            name: String::from(name),
+            span: history.span(),
+        }
+    }
+
+    pub fn limit_exceeded(msg: &str, history: History<'_>) -> Self {
+        Error::LimitExceeded {
+            message: String::from(msg),
+            span: history.span(),
+        }
+    }
+
+    // type_mismatch is a shortcut for Error::TypeMismatch
+    pub fn type_mismatch(expected: Kind, found: Kind) -> Self {
+        Error::TypeMismatch { expected, found }
+    }
+
+    // hint adds a hint to a parse error, other errors are left as is
+    pub fn hint(mut self, hint: &str) -> Self {
+        if let Error::Parse { hint: h, .. } = &mut self {
+            *h = Some(String::from(hint));
+        }
+        self
+    }
+
+    // span returns where in the query the error was found, this is only
+    // known for errors found while parsing
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::UnknownFunction { span, .. }
+            | Error::Parse { span, .. }
+            | Error::LimitExceeded { span, .. }
+            | Error::UnexpectedEOF { span } => Some(*span),
+            _ => None,
+        }
+    }
+
+    // message describes the error, without where it was found
+    pub fn message(&self) -> String {
+        match self {
+            Error::TypeMismatch { expected, found } => {
+                format!("expected {} but found {}", expected, found)
+            }
+            Error::UnknownFunction { name, .. } => format!("unknown function {}", name),
+            Error::Parse { message, .. } | Error::LimitExceeded { message, .. } => message.clone(),
+            Error::UnboundParameter { parameter } => {
+                format!("no value bound to parameter {}", parameter)
+            }
+            Error::UnexpectedEOF { .. } => String::from("unexpected end of query"),
+            Error::Arithmetic(msg) => msg.clone(),
+            Error::InvalidQuery(msg) => format!("invalid query: {}", msg),
+            Error::ExpressionError(msg) => msg.clone(),
+        }
+    }
+
+    // render formats the error for people reading the query, showing the
+    // line of the query the error was found on with the problem underlined:
+    //
+    // error: expected "FROM" but got "FORM"
+    //  --> 2:1
+    //   |
+    // 2 | FORM events
+    //   | ^^^^
+    pub fn render(&self, source: &str) -> String {
+        let mut out = format!("error: {}", self.message());
+
+        if let Some(span) = self.span() {
+            let pad = " ".repeat(span.line().to_string().len());
+            out.push_str(&format!("\n{pad}--> {span}\n{}", span.render(source)));
+
+            if let Error::Parse {
+                hint: Some(hint), ..
+            } = self
+            {
+                out.push_str(&format!("\n{pad} = hint: {hint}"));
+            }
+        }
+
+        out
     }
 }
 
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.span() {
+            Some(span) => write!(f, "{} at {}", self.message(), span),
+            None => write!(f, "{}", self.message()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 // History is used to wrap the content the lexor has already consumed. By making
 // this a type it is more likely that a developer in the future won't supply something
 // other than that, causing confusing error messages.
-pub struct History<'a>(&'a str, &'a str);
+pub struct History<'a> {
+    source: &'a str,
+    head: usize,
+    last: Range<usize>,
+}
 
 impl Display for History<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "{}", self.past())
     }
 }
 
 impl<'a> History<'a> {
-    pub fn new(past: &'a str, future: &'a str) -> Self {
-        Self(past.trim_end(), future.trim_start())
+    // new takes the entire query, how much of it has been consumed, and the
+    // byte range of the last token consumed
+    pub fn new(source: &'a str, head: usize, last: Range<usize>) -> Self {
+        Self { source, head, last }
+    }
+
+    // past returns the content which has been consumed
+    pub fn past(&self) -> &'a str {
+        &self.source[..self.head]
+    }
+
+    // future returns the content which has not been consumed
+    pub fn future(&self) -> &'a str {
+        &self.source[self.head..]
+    }
+
+    // span returns the span of the last token consumed
+    pub fn span(&self) -> Span {
+        Span::new(self.source, self.last.clone())
+    }
+
+    // since returns the span from start to the end of the last token
+    // consumed, for errors about several tokens
+    pub fn since(&self, start: usize) -> Span {
+        Span::new(self.source, start..self.last.end)
+    }
+
+    // end returns an empty span at the end of the query
+    pub fn end(&self) -> Span {
+        Span::new(self.source, self.source.len()..self.source.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_error_display() {
+        assert_eq!(
+            Error::type_mismatch(Kind::Number, Kind::Str).to_string(),
+            "expected number but found string"
+        );
+
+        let history = History::new("SELECT a\nFORM events", 13, 9..13);
+        assert_eq!(
+            Error::unknown_function("nope", History::new("nope(1)", 5, 0..4)).to_string(),
+            "unknown function nope at 1:1"
+        );
+        assert_eq!(
+            Error::with_history("bad token", history).to_string(),
+            "bad token at 2:1"
+        );
+
+        let err: Box<dyn std::error::Error> = Box::new(Error::Arithmetic(String::from("boom")));
+        assert_eq!(err.to_string(), "boom");
+    }
+
+    #[test]
+    fn test_error_render() {
+        let source = "SELECT a\nFORM events";
+        let err = Error::with_history(
+            "expected \"FROM\" but got \"FORM\"",
+            History::new(source, 13, 9..13),
+        )
+        .hint("clauses are written as FROM <namespace>");
+
+        assert_eq!(
+            err.render(source),
+            r#"error: expected "FROM" but got "FORM"
+ --> 2:1
+  |
+2 | FORM events
+  | ^^^^
+  = hint: clauses are written as FROM <namespace>"#
+        );
+
+        let err = Error::unexpected_eof(History::new("SELECT", 6, 0..6));
+        assert_eq!(
+            err.render("SELECT"),
+            "error: unexpected end of query\n --> 1:7\n  |\n1 | SELECT\n  |       ^"
+        );
+
+        let err = Error::Arithmetic(String::from("1 / 0 divides by zero"));
+        assert_eq!(err.render("SELECT 1 / 0"), "error: 1 / 0 divides by zero");
     }
 }
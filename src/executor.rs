@@ -0,0 +1,793 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fmt::Display,
+    hash::{Hash, Hasher},
+    time::Instant,
+};
+
+use crate::{
+    Aggregate, Any, Bound, Container, Kind, Parameter, Params, Projection, Query, Result, Str,
+};
+
+// Executor runs a query over containers pushed into it one at a time. Rows
+// are produced once every container has been pushed, with finish. Queries
+// that use a `GROUP BY` clause or aggregations produce a row per group,
+// everything else produces a row per matching container.
+pub struct Executor<'q> {
+    query: &'q Query,
+    params: Option<&'q Params>,
+    groups: Option<Groups>,
+    rows: Rows<'q>,
+}
+
+impl<'q> Executor<'q> {
+    pub fn new(query: &'q Query) -> Self {
+        Executor {
+            query,
+            params: None,
+            groups: Groups::new(query),
+            rows: Rows::new(query),
+        }
+    }
+
+    // with_params creates an executor which runs the query with the params
+    // bound to its parameters
+    pub fn with_params(query: &'q Query, params: &'q Params) -> Self {
+        Executor {
+            params: Some(params),
+            ..Executor::new(query)
+        }
+    }
+
+    // push evaluates a single container against the query
+    pub fn push<T: Container>(&mut self, c: &T) -> Result<()> {
+        let c = Bound::new(c, self.params);
+        if self.is_done() || !self.query.matches(&c)? {
+            return Ok(());
+        }
+
+        match &mut self.groups {
+            Some(groups) => {
+                groups.update(self.query, &c)?;
+            }
+            None => self.rows.push(&c)?,
+        }
+
+        Ok(())
+    }
+
+    // is_done returns true once the result can no longer change, so there
+    // is no need to push any more containers. This happens when a query
+    // that does not group or sort containers has reached its `LIMIT`.
+    pub fn is_done(&self) -> bool {
+        self.groups.is_none() && self.rows.is_full()
+    }
+
+    // finish returns the rows produced by the query, sorted by the `ORDER
+    // BY` clause. Rows which are equal, or when there is no `ORDER BY`
+    // clause, are returned in the order they were seen.
+    pub fn finish(self) -> Result<Vec<Any<'static>>> {
+        match &self.groups {
+            Some(groups) => groups.rows(self.query, self.params),
+            None => Ok(self.rows.finish()),
+        }
+    }
+}
+
+impl Query {
+    // execute runs the query over the containers, returning the produced
+    // rows. Each row is a map of projection name to value. Containers stop
+    // being pulled from the iterator as soon as the executor is done.
+    pub fn execute<T, I>(&self, containers: I) -> Result<Vec<Any<'static>>>
+    where
+        T: Container,
+        I: IntoIterator<Item = T>,
+    {
+        self.run(Executor::new(self), containers)
+    }
+
+    // execute_with runs the query like execute, with the params bound to
+    // the parameters of the query
+    pub fn execute_with<T, I>(&self, params: &Params, containers: I) -> Result<Vec<Any<'static>>>
+    where
+        T: Container,
+        I: IntoIterator<Item = T>,
+    {
+        self.run(Executor::with_params(self, params), containers)
+    }
+
+    fn run<T, I>(&self, mut executor: Executor<'_>, containers: I) -> Result<Vec<Any<'static>>>
+    where
+        T: Container,
+        I: IntoIterator<Item = T>,
+    {
+        for c in containers {
+            executor.push(&c)?;
+            if executor.is_done() {
+                break;
+            }
+        }
+
+        executor.finish()
+    }
+}
+
+// project evaluates the `SELECT` clause against the container, producing a
+// row. Wildcards copy every key of the container into the row.
+pub(crate) fn project<T: Container>(query: &Query, c: &T) -> Result<Any<'static>> {
+    let mut row = HashMap::new();
+
+    for projection in query.projections() {
+        match projection {
+            Projection::Wildcard => match c.value().into_owned() {
+                Any::Map(map) => row.extend(map),
+                Any::Null => {}
+                value => {
+                    row.insert(Str::from(projection.name()), value);
+                }
+            },
+            Projection::Expr { expr, .. } => {
+                let value = expr.evaluate(c)?.into_owned();
+                row.insert(Str::from(projection.name()), value);
+            }
+        }
+    }
+
+    Ok(Any::Map(row))
+}
+
+// Rows collects the rows produced by a query, applying the `HAVING`,
+// `ORDER BY`, `LIMIT` and `OFFSET` clauses. When there is a `LIMIT` clause
+// only the rows which could be returned are ever held, each row is inserted
+// into place as it is pushed rather than sorting every row at the end.
+#[derive(Debug)]
+pub(crate) struct Rows<'q> {
+    query: &'q Query,
+    rows: Vec<(Vec<Any<'static>>, Any<'static>)>,
+}
+
+impl<'q> Rows<'q> {
+    pub(crate) fn new(query: &'q Query) -> Self {
+        Rows {
+            query,
+            rows: Vec::new(),
+        }
+    }
+
+    // push projects the container into a row, when it passes the `HAVING`
+    // clause
+    pub(crate) fn push<T: Container>(&mut self, c: &T) -> Result<()> {
+        if !self.query.matches_having(c)? {
+            return Ok(());
+        }
+
+        let row = project(self.query, c)?;
+        let keys = {
+            let c = OrderContainer {
+                row: &row,
+                source: c,
+            };
+            self.query
+                .order()
+                .iter()
+                .map(|order| order.expr().evaluate(&c).map(Any::into_owned))
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let Some(limit) = self.capacity() else {
+            self.rows.push((keys, row));
+            return Ok(());
+        };
+
+        // rows which are equal to the new row stay in front of it, so the
+        // order rows were pushed in is kept
+        let index = self
+            .rows
+            .partition_point(|(other, _)| self.compare(other, &keys) != Ordering::Greater);
+        if index < limit {
+            self.rows.insert(index, (keys, row));
+            self.rows.truncate(limit);
+        }
+
+        Ok(())
+    }
+
+    // capacity returns the most rows which need to be held, this is None
+    // when there is no `LIMIT` clause
+    fn capacity(&self) -> Option<usize> {
+        self.query
+            .limit()
+            .map(|limit| limit.saturating_add(self.query.offset().unwrap_or_default()))
+    }
+
+    // is_full returns true when pushing more rows can not change the result,
+    // which is only the case when the rows are not being sorted
+    pub(crate) fn is_full(&self) -> bool {
+        self.query.order().is_empty()
+            && self
+                .capacity()
+                .is_some_and(|capacity| self.rows.len() >= capacity)
+    }
+
+    // compare orders two rows by their `ORDER BY` keys
+    fn compare(&self, lhs: &[Any<'_>], rhs: &[Any<'_>]) -> Ordering {
+        self.query
+            .order()
+            .iter()
+            .zip(lhs.iter().zip(rhs))
+            .map(|(order, (lhs, rhs))| order.compare(lhs, rhs))
+            .find(|ord| ord.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+
+    // finish returns the rows in order
+    pub(crate) fn finish(mut self) -> Vec<Any<'static>> {
+        if self.query.limit().is_none() && !self.query.order().is_empty() {
+            let mut rows = std::mem::take(&mut self.rows);
+            rows.sort_by(|(lhs, _), (rhs, _)| self.compare(lhs, rhs));
+            self.rows = rows;
+        }
+
+        self.rows
+            .into_iter()
+            .skip(self.query.offset().unwrap_or_default())
+            .map(|(_, row)| row)
+            .collect()
+    }
+}
+
+// OrderContainer is what `ORDER BY` keys are evaluated against. Keys are
+// looked up in the projected row first, so the names given with `AS` can be
+// used, and then in the container the row was projected from.
+#[derive(Debug)]
+struct OrderContainer<'r, T: Container> {
+    row: &'r Any<'static>,
+    source: &'r T,
+}
+
+impl<T: Container> Display for OrderContainer<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl<T: Container> Container for OrderContainer<'_, T> {
+    fn value(&self) -> Any<'_> {
+        self.source.value()
+    }
+
+    fn kind(&self) -> Kind {
+        self.source.kind()
+    }
+
+    fn get(&self, key: &str) -> Option<Any<'_>> {
+        self.row.get(key).or_else(|| self.source.get(key))
+    }
+
+    fn index(&self, index: usize) -> Option<Any<'_>> {
+        self.source.index(index)
+    }
+
+    fn children(&self) -> Box<dyn Iterator<Item = Any<'_>> + '_> {
+        self.source.children()
+    }
+
+    fn aggregate(&self, name: &str) -> Option<Any<'_>> {
+        self.source.aggregate(name)
+    }
+
+    fn parameter(&self, param: &Parameter) -> Option<Any<'_>> {
+        self.source.parameter(param)
+    }
+}
+
+// Groups holds the state of every group seen by a query, groups are kept
+// in the order they were first seen.
+#[derive(Debug)]
+pub(crate) struct Groups {
+    // names holds the name of each aggregation used by the query, it is
+    // used to lookup the results when evaluating against a group
+    names: Vec<String>,
+    aggregates: Vec<Aggregate>,
+    keys: HashMap<GroupKey, usize>,
+    groups: Vec<Group>,
+}
+
+// GroupKey is the value of the `GROUP BY` expressions for a group. Keys are
+// compared with total_cmp rather than ==, so numbers of different types
+// like 5 and 5.0 are the same key, and so are NaN keys.
+#[derive(Debug, Clone)]
+struct GroupKey(Vec<Any<'static>>);
+
+impl PartialEq for GroupKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self
+                .0
+                .iter()
+                .zip(&other.0)
+                .all(|(lhs, rhs)| lhs.total_cmp(rhs).is_eq())
+    }
+}
+
+impl Eq for GroupKey {}
+
+impl Hash for GroupKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl Groups {
+    // new returns None when the query does not group containers, either with
+    // a `GROUP BY` clause or an aggregation
+    pub(crate) fn new(query: &Query) -> Option<Self> {
+        let mut names = Vec::new();
+        let mut aggregates = Vec::new();
+        for agg in query.aggregates() {
+            let name = agg.to_string();
+            if !names.contains(&name) {
+                names.push(name);
+                aggregates.push(agg.aggregate());
+            }
+        }
+
+        if query.group().is_empty() && aggregates.is_empty() {
+            return None;
+        }
+
+        Some(Groups {
+            names,
+            aggregates,
+            keys: HashMap::new(),
+            groups: Vec::new(),
+        })
+    }
+
+    // insert adds a new group, the document is what non aggregated
+    // expressions are evaluated against
+    fn insert(&mut self, key: Vec<Any<'static>>, document: Any<'static>) -> usize {
+        self.keys.insert(GroupKey(key.clone()), self.groups.len());
+        self.groups.push(Group {
+            key,
+            document,
+            aggregates: self.aggregates.clone(),
+            updated: None,
+            triggered: false,
+        });
+        self.groups.len() - 1
+    }
+
+    // update adds the container to the group it belongs to, creating the
+    // group if this is the first container seen for it. The index of the
+    // group is returned.
+    pub(crate) fn update<T: Container>(&mut self, query: &Query, c: &T) -> Result<usize> {
+        let key = query
+            .group()
+            .iter()
+            .map(|expr| expr.evaluate(c).map(Any::into_owned))
+            .collect::<Result<Vec<_>>>()
+            .map(GroupKey)?;
+
+        let index = match self.keys.get(&key) {
+            Some(index) => *index,
+            None => self.insert(key.0, c.value().into_owned()),
+        };
+
+        for agg in self.groups[index].aggregates.iter_mut() {
+            agg.update(c)?;
+        }
+
+        Ok(index)
+    }
+
+    // touch records when the group was last updated
+    pub(crate) fn touch(&mut self, index: usize, now: Instant) {
+        self.groups[index].updated = Some(now);
+    }
+
+    // trigger records whether the `EMIT ON` condition matched the group,
+    // returning true only when it did not match the last time it was checked
+    pub(crate) fn trigger(&mut self, index: usize, matched: bool) -> bool {
+        let triggered = std::mem::replace(&mut self.groups[index].triggered, matched);
+        matched && !triggered
+    }
+
+    // evict removes every group which has not been updated since before
+    // the deadline
+    pub(crate) fn evict(&mut self, deadline: Instant) {
+        self.groups
+            .retain(|group| group.updated.is_some_and(|updated| updated >= deadline));
+
+        self.keys = self
+            .groups
+            .iter()
+            .enumerate()
+            .map(|(index, group)| (GroupKey(group.key.clone()), index))
+            .collect();
+    }
+
+    // container returns the group as a container, so expressions can be
+    // evaluated against it
+    pub(crate) fn container(&self, index: usize) -> GroupContainer<'_> {
+        GroupContainer {
+            group: &self.groups[index],
+            names: &self.names,
+        }
+    }
+
+    // rows projects every group into a result row, with the params bound to
+    // each group
+    pub(crate) fn rows(&self, query: &Query, params: Option<&Params>) -> Result<Vec<Any<'static>>> {
+        let mut rows = Rows::new(query);
+
+        // aggregating without grouping always returns a single row, even
+        // when nothing matched, so `COUNT(*)` can return 0.
+        if self.groups.is_empty() && query.group().is_empty() {
+            let group = Group {
+                key: Vec::new(),
+                document: Any::Null,
+                aggregates: self.aggregates.clone(),
+                updated: None,
+                triggered: false,
+            };
+            let c = GroupContainer {
+                group: &group,
+                names: &self.names,
+            };
+            rows.push(&Bound::new(c, params))?;
+        }
+
+        for index in 0..self.groups.len() {
+            rows.push(&Bound::new(self.container(index), params))?;
+        }
+
+        Ok(rows.finish())
+    }
+}
+
+// Group holds the state of a single group, the first document seen for the
+// group and the aggregations for the group.
+#[derive(Debug)]
+struct Group {
+    key: Vec<Any<'static>>,
+    document: Any<'static>,
+    aggregates: Vec<Aggregate>,
+    updated: Option<Instant>,
+    // triggered is whether the `EMIT ON` condition matched the group when
+    // it was last updated
+    triggered: bool,
+}
+
+// GroupContainer is what expressions are evaluated against once documents
+// have been grouped. Values are read from the first document of the group,
+// and aggregates are read from the aggregations of the group.
+#[derive(Debug)]
+pub(crate) struct GroupContainer<'g> {
+    group: &'g Group,
+    names: &'g [String],
+}
+
+impl Display for GroupContainer<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.group.document)
+    }
+}
+
+impl Container for GroupContainer<'_> {
+    fn value(&self) -> Any<'_> {
+        self.group.document.value()
+    }
+
+    fn kind(&self) -> Kind {
+        self.group.document.kind()
+    }
+
+    fn get(&self, key: &str) -> Option<Any<'_>> {
+        self.group.document.get(key)
+    }
+
+    fn index(&self, index: usize) -> Option<Any<'_>> {
+        self.group.document.index(index)
+    }
+
+    fn children(&self) -> Box<dyn Iterator<Item = Any<'_>> + '_> {
+        self.group.document.children()
+    }
+
+    fn aggregate(&self, name: &str) -> Option<Any<'_>> {
+        let index = self.names.iter().position(|n| n == name)?;
+        Some(self.group.aggregates[index].result())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Parser;
+
+    const EVENTS: &str = r#"[
+        {"host": "a", "status": 200, "latency": 10},
+        {"host": "b", "status": 500, "latency": 30},
+        {"host": "a", "status": 500, "latency": 20},
+        {"host": "a", "status": 200, "latency": null},
+        {"host": "c", "status": 200}
+    ]"#;
+
+    macro_rules! assert_execute {
+        ( $query:expr, $expected:expr ) => {
+            let events: Vec<Any> = serde_json::from_str(EVENTS).unwrap();
+            let query = Parser::from($query).query()?;
+            let rows = query.execute(&events)?;
+            let expected: Vec<Any> = serde_json::from_str($expected).unwrap();
+            assert_eq!(rows, expected, "{}", $query);
+        };
+    }
+
+    #[test]
+    fn test_execute() -> Result<()> {
+        assert_execute!(
+            "SELECT host, status AS code FROM events WHERE latency > 15",
+            r#"[{"host": "b", "code": 500}, {"host": "a", "code": 500}]"#
+        );
+        assert_execute!(
+            "SELECT * FROM events WHERE host = 'c'",
+            r#"[{"host": "c", "status": 200}]"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_group() -> Result<()> {
+        assert_execute!(
+            "SELECT host, COUNT(*) AS count, COUNT(latency) AS measured, SUM(latency) AS total, AVG(latency) AS avg FROM events GROUP BY host",
+            r#"[
+                {"host": "a", "count": 3, "measured": 2, "total": 30, "avg": 15.0},
+                {"host": "b", "count": 1, "measured": 1, "total": 30, "avg": 30.0},
+                {"host": "c", "count": 1, "measured": 0, "total": null, "avg": null}
+            ]"#
+        );
+        assert_execute!(
+            "SELECT host, status, COUNT(*) AS count FROM events WHERE status = 200 GROUP BY host, status",
+            r#"[
+                {"host": "a", "status": 200, "count": 2},
+                {"host": "c", "status": 200, "count": 1}
+            ]"#
+        );
+        assert_execute!(
+            "SELECT SUM(latency) / COUNT(latency) AS avg, COUNT(*) FROM events",
+            r#"[{"avg": 20, "COUNT(*)": 5}]"#
+        );
+        assert_execute!(
+            "SELECT COUNT(*) AS count FROM events WHERE host = 'd'",
+            r#"[{"count": 0}]"#
+        );
+        assert_execute!(
+            "SELECT host, COUNT(*) AS count FROM events WHERE host = 'd' GROUP BY host",
+            r#"[]"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_group_numbers() -> Result<()> {
+        let events: Vec<Any> =
+            serde_json::from_str(r#"[{"n": 5}, {"n": 5.0}, {"n": 0}, {"n": -0.0}, {"n": 5.5}]"#)
+                .unwrap();
+
+        let query = Parser::from("SELECT n, COUNT(*) AS count FROM events GROUP BY n").query()?;
+        let expected: Vec<Any> = serde_json::from_str(
+            r#"[{"n": 5, "count": 2}, {"n": 0, "count": 2}, {"n": 5.5, "count": 1}]"#,
+        )
+        .unwrap();
+        assert_eq!(query.execute(&events)?, expected);
+
+        // n % 0.0 is NaN for every n
+        let query =
+            Parser::from("SELECT COUNT(*) AS count FROM events GROUP BY n % 0.0").query()?;
+        let expected: Vec<Any> = serde_json::from_str(r#"[{"count": 5}]"#).unwrap();
+        assert_eq!(query.execute(&events)?, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_having() -> Result<()> {
+        assert_execute!(
+            "SELECT host FROM events GROUP BY host HAVING COUNT(*) > 1",
+            r#"[{"host": "a"}]"#
+        );
+        assert_execute!(
+            "SELECT host, SUM(latency) AS total FROM events GROUP BY host HAVING SUM(latency) >= 30 AND host != 'b'",
+            r#"[{"host": "a", "total": 30}]"#
+        );
+        assert_execute!(
+            "SELECT host FROM events GROUP BY host HAVING NOT (host = 'a' OR AVG(latency) = NULL)",
+            r#"[{"host": "b"}]"#
+        );
+        assert_execute!(
+            "SELECT COUNT(*) AS count FROM events HAVING COUNT(*) > 10",
+            r#"[]"#
+        );
+        assert_execute!(
+            "SELECT host FROM events WHERE status = 500 HAVING latency < 30",
+            r#"[{"host": "a"}]"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_order() -> Result<()> {
+        assert_execute!(
+            "SELECT host, latency FROM events ORDER BY latency DESC",
+            r#"[
+                {"host": "b", "latency": 30},
+                {"host": "a", "latency": 20},
+                {"host": "a", "latency": 10},
+                {"host": "a", "latency": null},
+                {"host": "c", "latency": null}
+            ]"#
+        );
+        assert_execute!(
+            "SELECT host, latency FROM events ORDER BY latency NULLS LAST",
+            r#"[
+                {"host": "a", "latency": 10},
+                {"host": "a", "latency": 20},
+                {"host": "b", "latency": 30},
+                {"host": "a", "latency": null},
+                {"host": "c", "latency": null}
+            ]"#
+        );
+        assert_execute!(
+            "SELECT host, status FROM events ORDER BY host DESC, status ASC, latency DESC NULLS FIRST",
+            r#"[
+                {"host": "c", "status": 200},
+                {"host": "b", "status": 500},
+                {"host": "a", "status": 200},
+                {"host": "a", "status": 200},
+                {"host": "a", "status": 500}
+            ]"#
+        );
+        assert_execute!(
+            "SELECT host, COUNT(*) AS count FROM events GROUP BY host ORDER BY count DESC, host DESC",
+            r#"[
+                {"host": "a", "count": 3},
+                {"host": "c", "count": 1},
+                {"host": "b", "count": 1}
+            ]"#
+        );
+        assert_execute!(
+            "SELECT host FROM events GROUP BY host ORDER BY SUM(latency) DESC NULLS LAST",
+            r#"[{"host": "a"}, {"host": "b"}, {"host": "c"}]"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_order_limit() -> Result<()> {
+        assert_execute!(
+            "SELECT host, latency FROM events ORDER BY latency DESC LIMIT 2",
+            r#"[{"host": "b", "latency": 30}, {"host": "a", "latency": 20}]"#
+        );
+        assert_execute!(
+            "SELECT host FROM events ORDER BY host LIMIT 3",
+            r#"[{"host": "a"}, {"host": "a"}, {"host": "a"}]"#
+        );
+        assert_execute!(
+            "SELECT host, COUNT(*) AS count FROM events GROUP BY host ORDER BY count LIMIT 1",
+            r#"[{"host": "b", "count": 1}]"#
+        );
+
+        // incomparable values are ordered by their kind
+        let values: Vec<Any> =
+            serde_json::from_str(r#"[{"v": "a"}, {"v": [1]}, {"v": 2}, {"v": true}, {"v": {}}]"#)
+                .unwrap();
+        let query = Parser::from("SELECT v FROM values ORDER BY v LIMIT 4").query()?;
+        let expected: Vec<Any> =
+            serde_json::from_str(r#"[{"v": "a"}, {"v": 2}, {"v": true}, {"v": [1]}]"#).unwrap();
+        assert_eq!(query.execute(&values)?, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_limit() -> Result<()> {
+        assert_execute!(
+            "SELECT host FROM events LIMIT 2",
+            r#"[{"host": "a"}, {"host": "b"}]"#
+        );
+        assert_execute!(
+            "SELECT host FROM events LIMIT 2 OFFSET 1",
+            r#"[{"host": "b"}, {"host": "a"}]"#
+        );
+        assert_execute!(
+            "SELECT host FROM events OFFSET 3",
+            r#"[{"host": "a"}, {"host": "c"}]"#
+        );
+        assert_execute!("SELECT host FROM events LIMIT 2 OFFSET 10", r#"[]"#);
+        assert_execute!(
+            "SELECT host, latency FROM events ORDER BY latency DESC LIMIT 1 OFFSET 1",
+            r#"[{"host": "a", "latency": 20}]"#
+        );
+        assert_execute!(
+            "SELECT host, COUNT(*) AS count FROM events GROUP BY host LIMIT 5 OFFSET 2",
+            r#"[{"host": "c", "count": 1}]"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_early_termination() -> Result<()> {
+        let events: Vec<Any> = serde_json::from_str(EVENTS).unwrap();
+        let mut pulled = 0;
+
+        let query = Parser::from("SELECT host FROM events WHERE status = 500 LIMIT 1").query()?;
+        let rows = query.execute(events.iter().inspect(|_| pulled += 1))?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(pulled, 2);
+
+        // sorting and grouping need to see every container
+        pulled = 0;
+        let query = Parser::from("SELECT host FROM events ORDER BY host LIMIT 1").query()?;
+        query.execute(events.iter().inspect(|_| pulled += 1))?;
+        assert_eq!(pulled, events.len());
+
+        pulled = 0;
+        let query = Parser::from("SELECT COUNT(*) FROM events LIMIT 1").query()?;
+        query.execute(events.iter().inspect(|_| pulled += 1))?;
+        assert_eq!(pulled, events.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_errors() -> Result<()> {
+        let events: Vec<Any> = serde_json::from_str(EVENTS).unwrap();
+
+        let query = Parser::from("SELECT SUM(host) FROM events").query()?;
+        assert!(query.execute(&events).is_err());
+
+        let query = Parser::from("SELECT * FROM events WHERE COUNT(*) > 1").query()?;
+        assert!(query.execute(&events).is_err());
+
+        let query = Parser::from("SELECT * FROM events WHERE host = :host").query()?;
+        assert!(query.execute(&events).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_params() -> Result<()> {
+        let events: Vec<Any> = serde_json::from_str(EVENTS).unwrap();
+        let query = Parser::from(
+            "SELECT host, COUNT(*) AS count FROM events WHERE status = $1 GROUP BY host HAVING COUNT(*) >= :min",
+        )
+        .query()?;
+
+        let params = Params::new().bind(1, 200).bind("min", 2);
+        let rows = query.execute_with(&params, &events)?;
+        let expected: Vec<Any> = serde_json::from_str(r#"[{"host": "a", "count": 2}]"#).unwrap();
+        assert_eq!(rows, expected);
+
+        // the same query can be run again with other values
+        let params = Params::new().bind(1, 500).bind("min", 1);
+        let rows = query.execute_with(&params, &events)?;
+        let expected: Vec<Any> =
+            serde_json::from_str(r#"[{"host": "b", "count": 1}, {"host": "a", "count": 1}]"#)
+                .unwrap();
+        assert_eq!(rows, expected);
+
+        // values are never parsed as part of the query
+        let query = Parser::from("SELECT host FROM events WHERE host = :host").query()?;
+        let params = Params::new().bind("host", "a' OR host != 'a");
+        assert!(query.execute_with(&params, &events)?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,301 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Display};
+
+use crate::{
+    Any, Container, Error, Expr, Kind, Number, Parser, Result,
+    parser::{
+        AGGREGATION_AVG, AGGREGATION_COUNT, AGGREGATION_SUM, FN_CLOSE, FN_OPEN, MULTIPLY,
+        consume_next, continue_if, must_token,
+    },
+};
+
+use super::Expression;
+
+// Aggregation is a trait that folds many containers into a single value.
+// Each container is passed to update, and result returns the value
+// aggregated so far. Aggregations hold their own state, so a fresh
+// aggregation is needed for every group.
+pub trait Aggregation: Display + Debug + Send + Sync + Clone {
+    fn update<T: Container>(&mut self, c: &T) -> Result<()>;
+    fn result(&self) -> Any<'static>;
+}
+
+macro_rules! aggregation_impl {
+    ($( $i:ident ),* ) => {
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        #[serde(tag = "type", rename_all = "snake_case")]
+        pub enum Aggregate {
+            $( $i($i), )*
+        }
+
+        impl Aggregate {
+            pub fn update<T: Container>(&mut self, c: &T) -> Result<()> {
+                match self {
+                    $( Aggregate::$i(agg) => agg.update(c), )*
+                }
+            }
+
+            pub fn result(&self) -> Any<'static> {
+                match self {
+                    $( Aggregate::$i(agg) => agg.result(), )*
+                }
+            }
+
+            // argument returns the expression the aggregation is applied to
+            pub fn argument(&self) -> Option<&Expr> {
+                match self {
+                    $( Aggregate::$i(agg) => agg.expr.as_deref(), )*
+                }
+            }
+
+            pub fn argument_mut(&mut self) -> Option<&mut Expr> {
+                match self {
+                    $( Aggregate::$i(agg) => agg.expr.as_deref_mut(), )*
+                }
+            }
+        }
+
+        impl Display for Aggregate {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $( Aggregate::$i(agg) => Display::fmt(agg, f), )*
+                }
+            }
+        }
+
+        $(
+            impl From<$i> for Aggregate {
+                fn from(val: $i) -> Self {
+                    Aggregate::$i(val)
+                }
+            }
+        )*
+    };
+}
+
+aggregation_impl!(SumAggregation, CountAggregation, AvgAggregation);
+
+// write_aggregation writes out an aggregation as a function call, a missing
+// expression is written as `*`
+fn write_aggregation(
+    f: &mut std::fmt::Formatter<'_>,
+    name: &str,
+    expr: &Option<Box<Expr>>,
+) -> std::fmt::Result {
+    match expr {
+        Some(expr) => write!(f, "{}{}{}{}", name, FN_OPEN, expr, FN_CLOSE),
+        None => write!(f, "{}{}{}{}", name, FN_OPEN, MULTIPLY, FN_CLOSE),
+    }
+}
+
+// number_or_null evaluates the expression, returning None when the value is
+// null so it can be skipped. Any other non numeric value is an error.
+fn number_or_null<T: Container>(expr: &Option<Box<Expr>>, c: &T) -> Result<Option<Number>> {
+    let Some(expr) = expr else {
+        return Ok(None);
+    };
+
+    match expr.evaluate(c)? {
+        Any::Null => Ok(None),
+        Any::Number(num) => Ok(Some(num)),
+        value => Err(Error::type_mismatch(Kind::Number, value.kind())),
+    }
+}
+
+// SumAggregation adds together every value, null values are skipped. When
+// no values were seen the result is null.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SumAggregation {
+    expr: Option<Box<Expr>>,
+    #[serde(skip)]
+    sum: Option<Number>,
+}
+
+impl SumAggregation {
+    pub fn new(expr: Expr) -> Self {
+        SumAggregation {
+            expr: Some(Box::new(expr)),
+            sum: None,
+        }
+    }
+}
+
+impl Aggregation for SumAggregation {
+    fn update<T: Container>(&mut self, c: &T) -> Result<()> {
+        if let Some(num) = number_or_null(&self.expr, c)? {
+            self.sum = Some(match self.sum {
+                Some(sum) => sum + num,
+                None => num,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn result(&self) -> Any<'static> {
+        self.sum.map(Any::from).unwrap_or_default()
+    }
+}
+
+impl Display for SumAggregation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_aggregation(f, AGGREGATION_SUM, &self.expr)
+    }
+}
+
+// CountAggregation counts the values which are not null. `COUNT(*)` counts
+// every container.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CountAggregation {
+    expr: Option<Box<Expr>>,
+    #[serde(skip)]
+    count: u64,
+}
+
+impl CountAggregation {
+    pub fn new(expr: Option<Expr>) -> Self {
+        CountAggregation {
+            expr: expr.map(Box::new),
+            count: 0,
+        }
+    }
+}
+
+impl Aggregation for CountAggregation {
+    fn update<T: Container>(&mut self, c: &T) -> Result<()> {
+        let counted = match &self.expr {
+            Some(expr) => !matches!(expr.evaluate(c)?, Any::Null),
+            None => true,
+        };
+
+        if counted {
+            self.count += 1;
+        }
+
+        Ok(())
+    }
+
+    fn result(&self) -> Any<'static> {
+        Any::from(self.count)
+    }
+}
+
+impl Display for CountAggregation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_aggregation(f, AGGREGATION_COUNT, &self.expr)
+    }
+}
+
+// AvgAggregation averages every value, null values are skipped. The result
+// is always a float, or null when no values were seen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvgAggregation {
+    expr: Option<Box<Expr>>,
+    #[serde(skip)]
+    sum: f64,
+    #[serde(skip)]
+    count: u64,
+}
+
+impl AvgAggregation {
+    pub fn new(expr: Expr) -> Self {
+        AvgAggregation {
+            expr: Some(Box::new(expr)),
+            sum: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl Aggregation for AvgAggregation {
+    fn update<T: Container>(&mut self, c: &T) -> Result<()> {
+        if let Some(num) = number_or_null(&self.expr, c)? {
+            self.sum += f64::from(num);
+            self.count += 1;
+        }
+
+        Ok(())
+    }
+
+    fn result(&self) -> Any<'static> {
+        if self.count == 0 {
+            return Any::Null;
+        }
+
+        Any::from(self.sum / self.count as f64)
+    }
+}
+
+impl Display for AvgAggregation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_aggregation(f, AGGREGATION_AVG, &self.expr)
+    }
+}
+
+// AggregateExpression makes it possible to use an aggregation within an
+// expression, like `SUM(a) / COUNT(*)`. The aggregation it holds is never
+// updated, it is cloned for every group. When evaluated the result is looked
+// up from the container with Container::aggregate, which is only available
+// once documents have been grouped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregateExpression {
+    aggregate: Aggregate,
+}
+
+impl AggregateExpression {
+    pub fn new(aggregate: Aggregate) -> Self {
+        AggregateExpression { aggregate }
+    }
+
+    // aggregate returns a fresh aggregation for this expression
+    pub fn aggregate(&self) -> Aggregate {
+        self.aggregate.clone()
+    }
+}
+
+impl Expression for AggregateExpression {
+    fn evaluate<'a: 'b, 'b, T: Container>(&'a self, c: &'b T) -> Result<Any<'b>> {
+        c.aggregate(&self.to_string()).ok_or_else(|| {
+            Error::ExpressionError(format!(
+                "{} can only be used once documents are aggregated",
+                self
+            ))
+        })
+    }
+
+    fn children(&self) -> Vec<&Expr> {
+        self.aggregate.argument().into_iter().collect()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Expr> {
+        self.aggregate.argument_mut().into_iter().collect()
+    }
+}
+
+impl Display for AggregateExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.aggregate)
+    }
+}
+
+impl<'a> Parser<'a> {
+    // aggregate_expression parses SUM, COUNT and AVG
+    pub(crate) fn aggregate_expression(&self) -> Result<AggregateExpression> {
+        let name = must_token!(self)?.text().to_uppercase();
+        consume_next!(self, FN_OPEN)?;
+        self.must_argument(0)?;
+
+        let aggregate = match name.as_str() {
+            AGGREGATION_SUM => Aggregate::from(SumAggregation::new(self.expression()?)),
+            AGGREGATION_AVG => Aggregate::from(AvgAggregation::new(self.expression()?)),
+            AGGREGATION_COUNT if continue_if!(self, MULTIPLY) => {
+                Aggregate::from(CountAggregation::new(None))
+            }
+            AGGREGATION_COUNT => Aggregate::from(CountAggregation::new(Some(self.expression()?))),
+            _ => return Err(Error::unknown_function(&name, self.history())),
+        };
+
+        consume_next!(self, FN_CLOSE)?;
+        Ok(AggregateExpression::new(aggregate))
+    }
+}
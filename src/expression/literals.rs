@@ -1,23 +1,21 @@
-use std::{
-    collections::HashMap,
-    fmt::{Debug, Display},
-    ops::Deref,
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Display, ops::Deref};
+
+use crate::{
+    Any, Container, Number, Result, Str,
+    lexor::escape,
+    parser::{
+        ARRAY_CHILD_SEP, ARRAY_WRAP, ARRAY_WRAP_END, FALSE, MAP_CHILD_SEP, MAP_CHILD_SET, MAP_WRAP,
+        MAP_WRAP_END, NULL, STRING_WRAP, TRUE,
+    },
 };
 
-use crate::{Any, Container, Number, Result, Str, parser::STRING_WRAP};
-
 use super::{Expr, Expression};
 
 // NullExpression is an expression that returns a null value.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct NullExpression {}
 
-impl Default for NullExpression {
-    fn default() -> Self {
-        NullExpression {}
-    }
-}
-
 impl Expression for NullExpression {
     fn evaluate<'a: 'b, 'b, T: Container>(&'a self, _: &'b T) -> Result<Any<'b>> {
         Ok(Any::Null)
@@ -26,12 +24,12 @@ impl Expression for NullExpression {
 
 impl Display for NullExpression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        Debug::fmt(self, f)
+        write!(f, "{}", NULL)
     }
 }
 
 // StringExpression makes a literal string an expression.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StringLiteral {
     value: String,
 }
@@ -54,7 +52,13 @@ impl Expression for StringLiteral {
 
 impl Display for StringLiteral {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}{}{}", STRING_WRAP, self.value, STRING_WRAP)
+        write!(
+            f,
+            "{}{}{}",
+            STRING_WRAP,
+            escape(&self.value, STRING_WRAP),
+            STRING_WRAP
+        )
     }
 }
 
@@ -88,7 +92,7 @@ impl_string_literal_from!(&str);
 impl_string_literal_from!(Str<'a>);
 
 // NumberExpression makes a literal string an expression.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NumberLiteral {
     value: Number,
 }
@@ -143,7 +147,7 @@ impl_number_literal_from!(f32);
 impl_number_literal_from!(f64);
 impl_number_literal_from!(Number);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BoolLiteral {
     value: bool,
 }
@@ -166,21 +170,19 @@ impl Expression for BoolLiteral {
 
 impl Display for BoolLiteral {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.value)
+        write!(f, "{}", if self.value { TRUE } else { FALSE })
     }
 }
 
-impl<'a> From<bool> for BoolLiteral {
+impl From<bool> for BoolLiteral {
     fn from(value: bool) -> Self {
-        BoolLiteral {
-            value: value.into(),
-        }
+        BoolLiteral { value }
     }
 }
 
 impl_deref_for_literal!(BoolLiteral, bool);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MapLiteral {
     value: HashMap<String, Expr>,
 }
@@ -208,11 +210,31 @@ impl Expression for MapLiteral {
                 .collect::<HashMap<Str<'a>, Any<'b>>>(),
         ))
     }
+
+    fn children(&self) -> Vec<&Expr> {
+        self.value.values().collect()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Expr> {
+        self.value.values_mut().collect()
+    }
 }
 
+// Display writes the keys in order, so the same map is always written the
+// same way
 impl Display for MapLiteral {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self.value)
+        let mut entries = self.value.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|(k, _)| *k);
+
+        write!(f, "{}", MAP_WRAP)?;
+        for (i, (k, v)) in entries.into_iter().enumerate() {
+            if i > 0 {
+                write!(f, "{} ", MAP_CHILD_SEP)?;
+            }
+            write!(f, "{}{} {}", Str::from(k), MAP_CHILD_SET, v)?;
+        }
+        write!(f, "{}", MAP_WRAP_END)
     }
 }
 
@@ -237,7 +259,7 @@ macro_rules! impl_map_literal_from {
 }
 impl_map_literal_from!(HashMap<String, Expr>);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListLiteral {
     value: Vec<Expr>,
 }
@@ -259,15 +281,30 @@ impl Expression for ListLiteral {
         Ok(Any::from(
             self.value
                 .iter()
-                .filter_map(|v| Some(v.evaluate(c).ok()?))
+                .filter_map(|v| v.evaluate(c).ok())
                 .collect::<Vec<Any<'b>>>(),
         ))
     }
+
+    fn children(&self) -> Vec<&Expr> {
+        self.value.iter().collect()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Expr> {
+        self.value.iter_mut().collect()
+    }
 }
 
 impl Display for ListLiteral {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self.value)
+        write!(f, "{}", ARRAY_WRAP)?;
+        for (i, v) in self.value.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{} ", ARRAY_CHILD_SEP)?;
+            }
+            write!(f, "{}", v)?;
+        }
+        write!(f, "{}", ARRAY_WRAP_END)
     }
 }
 
@@ -1,13 +1,18 @@
+use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
-use crate::{Any, Container, Expr, Number, Result};
+use crate::{
+    Any, Container, Error, Expr, Number, Result,
+    parser::{ADD, DIVIDE, EXPONENT, MINUS, MODULUS, MULTIPLY, SUB_EXPR_CLOSE, SUB_EXPR_OPEN},
+};
 
 use super::Expression;
 
-// Math expressions
+// Math expressions, integer math that overflows or divides by zero returns
+// Error::Arithmetic rather than wrapping
 macro_rules! impl_expression_math_op {
-    ($name:ident, $op:tt) => {
-        #[derive(Debug, Clone)]
+    ($name:ident, $op:ident, $checked:ident, $precedence:ident) => {
+        #[derive(Debug, Clone, Serialize, Deserialize)]
         pub struct $name {
             left: Box<Expr>,
             right: Box<Expr>,
@@ -15,7 +20,10 @@ macro_rules! impl_expression_math_op {
 
         impl $name {
             pub fn new(left: Expr, right: Expr) -> Self {
-                Self { left: Box::new(left), right: Box::new(right) }
+                Self {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                }
             }
         }
 
@@ -24,26 +32,115 @@ macro_rules! impl_expression_math_op {
                 let left: Number = self.left.evaluate(d)?.try_into()?;
                 let right: Number = self.right.evaluate(d)?.try_into()?;
 
-                Ok(Any::Number(left $op right))
+                left.$checked(right)
+                    .map(Any::Number)
+                    .ok_or_else(|| arithmetic_error(self, right))
             }
 
+            fn children(&self) -> Vec<&Expr> {
+                vec![&self.left, &self.right]
+            }
+
+            fn children_mut(&mut self) -> Vec<&mut Expr> {
+                vec![&mut self.left, &mut self.right]
+            }
         }
 
         impl Display for $name {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                write!(f, "{} {} {}", self.left, stringify!($op), self.right)
+                write_operation(f, &self.left, $op, &self.right, $precedence)
             }
         }
     };
 }
 
-impl_expression_math_op!(ModulusExpression, %);
-impl_expression_math_op!(DivideExpression, /);
-impl_expression_math_op!(MultiplyExpression, *);
-impl_expression_math_op!(AddExpression, +);
-impl_expression_math_op!(SubtractExpression, -);
+impl_expression_math_op!(ModulusExpression, MODULUS, checked_rem, PRECEDENCE_MULTIPLY);
+impl_expression_math_op!(DivideExpression, DIVIDE, checked_div, PRECEDENCE_MULTIPLY);
+impl_expression_math_op!(
+    MultiplyExpression,
+    MULTIPLY,
+    checked_mul,
+    PRECEDENCE_MULTIPLY
+);
+impl_expression_math_op!(AddExpression, ADD, checked_add, PRECEDENCE_ADD);
+impl_expression_math_op!(SubtractExpression, MINUS, checked_sub, PRECEDENCE_ADD);
+
+// Precedence of the math operators, operators with a higher precedence bind
+// tighter. Everything that isn't a math operation binds tightest, apart from
+// negative numbers which are written like the unary operator `-`.
+pub(crate) const PRECEDENCE_ADD: u8 = 1;
+pub(crate) const PRECEDENCE_MULTIPLY: u8 = 2;
+pub(crate) const PRECEDENCE_UNARY: u8 = 3;
+pub(crate) const PRECEDENCE_EXPONENT: u8 = 4;
+const PRECEDENCE_OPERAND: u8 = 5;
+
+pub(crate) fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::AddExpression(_) | Expr::SubtractExpression(_) => PRECEDENCE_ADD,
+        Expr::MultiplyExpression(_) | Expr::DivideExpression(_) | Expr::ModulusExpression(_) => {
+            PRECEDENCE_MULTIPLY
+        }
+        Expr::ExponentExpression(_) => PRECEDENCE_EXPONENT,
+        Expr::NegativeExpression(_) | Expr::PositiveExpression(_) | Expr::NotExpression(_) => {
+            PRECEDENCE_UNARY
+        }
+        Expr::NumberLiteral(num) => match **num {
+            Number::Integer(v) if v < 0 => PRECEDENCE_UNARY,
+            Number::Float(v) if v.is_finite() && v.is_sign_negative() => PRECEDENCE_UNARY,
+            _ => PRECEDENCE_OPERAND,
+        },
+        _ => PRECEDENCE_OPERAND,
+    }
+}
+
+// leads_with_not returns true when the expression is written starting with
+// NOT. At the start of a comparison that would be read as a NOT condition,
+// so comparisons wrap it in parenthesis.
+pub(crate) fn leads_with_not(expr: &Expr) -> bool {
+    match expr {
+        Expr::NotExpression(_) => true,
+        expr if precedence(expr) < PRECEDENCE_UNARY => expr
+            .children()
+            .first()
+            .is_some_and(|left| leads_with_not(left)),
+        _ => false,
+    }
+}
 
-#[derive(Debug, Clone)]
+// write_operation writes a math operation, wrapping operands in parentheses
+// when they would otherwise be parsed into a different tree. Operators are
+// left associative, so the right operand is also wrapped when it has the
+// same precedence, like `a - (b - c)`.
+fn write_operation(
+    f: &mut std::fmt::Formatter<'_>,
+    left: &Expr,
+    op: &str,
+    right: &Expr,
+    op_precedence: u8,
+) -> std::fmt::Result {
+    write_operand(f, left, precedence(left) < op_precedence)?;
+    write!(f, " {} ", op)?;
+    write_operand(f, right, precedence(right) <= op_precedence)
+}
+
+fn write_operand(f: &mut std::fmt::Formatter<'_>, expr: &Expr, wrap: bool) -> std::fmt::Result {
+    if wrap {
+        write!(f, "{}{}{}", SUB_EXPR_OPEN, expr, SUB_EXPR_CLOSE)
+    } else {
+        write!(f, "{}", expr)
+    }
+}
+
+// arithmetic_error explains why a checked operation failed
+fn arithmetic_error<E: Display>(expr: &E, right: Number) -> Error {
+    if matches!(right, Number::Integer(0) | Number::UInteger(0)) {
+        Error::Arithmetic(format!("{} divides by zero", expr))
+    } else {
+        Error::Arithmetic(format!("{} overflows", expr))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExponentExpression {
     left: Box<Expr>,
     right: Box<Expr>,
@@ -63,17 +160,39 @@ impl Expression for ExponentExpression {
         let left: Number = self.left.evaluate(d)?.try_into()?;
         let right: Number = self.right.evaluate(d)?.try_into()?;
 
-        Ok(Any::from(i64::from(left).pow(u32::from(right))))
+        left.checked_pow(right).map(Any::Number).ok_or_else(|| {
+            // 0 raised to a negative power is 1 divided by 0
+            if f64::from(left) == 0.0 && f64::from(right) < 0.0 {
+                Error::Arithmetic(format!("{} divides by zero", self))
+            } else {
+                Error::Arithmetic(format!("{} overflows", self))
+            }
+        })
+    }
+
+    fn children(&self) -> Vec<&Expr> {
+        vec![&self.left, &self.right]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Expr> {
+        vec![&mut self.left, &mut self.right]
     }
 }
 
+// ^ is right associative, so unlike the other operators the left operand
+// is wrapped when it has the same precedence, like `(a ^ b) ^ c`. The right
+// operand can be a unary expression without parenthesis, like `a ^ -b`.
 impl Display for ExponentExpression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} {} {}", self.left, stringify!(EXPONENT), self.right)
+        let left = &self.left;
+        let right = &self.right;
+        write_operand(f, left, precedence(left) <= PRECEDENCE_EXPONENT)?;
+        write!(f, " {} ", EXPONENT)?;
+        write_operand(f, right, precedence(right) < PRECEDENCE_UNARY)
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubExpression {
     expr: Box<Expr>,
 }
@@ -90,10 +209,18 @@ impl Expression for SubExpression {
     fn evaluate<'a: 'b, 'b, T: Container>(&'a self, d: &'b T) -> Result<Any<'b>> {
         self.expr.evaluate(d)
     }
+
+    fn children(&self) -> Vec<&Expr> {
+        vec![&self.expr]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Expr> {
+        vec![&mut self.expr]
+    }
 }
 
 impl Display for SubExpression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({})", self.expr)
+        write!(f, "{}{}{}", SUB_EXPR_OPEN, self.expr, SUB_EXPR_CLOSE)
     }
 }
@@ -1,11 +1,20 @@
+mod aggregate;
 mod literals;
 mod math;
+mod parameter;
+mod path;
 mod string;
+mod unary;
 
+pub use aggregate::*;
 pub use literals::*;
 pub use math::*;
+pub use parameter::*;
+pub use path::*;
+use serde::{Deserialize, Serialize};
 use std::fmt::{Debug, Display};
 pub use string::*;
+pub use unary::*;
 
 use crate::{Any, Container, Parser, error::Result};
 
@@ -14,21 +23,46 @@ use crate::{Any, Container, Parser, error::Result};
 // can return.
 pub trait Expression: Display + Debug + Send + Sync + Clone {
     fn evaluate<'a: 'b, 'b, T: Container>(&'a self, c: &'b T) -> Result<Any<'b>>;
+
+    // children returns the expressions nested directly within this
+    // expression, which makes it possible to walk the expression tree.
+    fn children(&self) -> Vec<&Expr> {
+        Vec::new()
+    }
+
+    // children_mut is children, but allows the nested expressions to be
+    // rewritten
+    fn children_mut(&mut self) -> Vec<&mut Expr> {
+        Vec::new()
+    }
 }
 
 macro_rules! expr_impl {
     ($( $i:ident ),* ) => {
-        #[derive(Debug, Clone)]
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        #[serde(tag = "type", rename_all = "snake_case")]
         pub enum Expr {
             $( $i($i), )*
         }
 
         impl Expr {
-            fn evaluate<'a: 'b, 'b, T: Container>(&'a self, c: &'b T) -> Result<Any<'b>> {
+            pub fn evaluate<'a: 'b, 'b, T: Container>(&'a self, c: &'b T) -> Result<Any<'b>> {
                 match self {
                     $( Expr::$i(expr) => expr.evaluate(c), )*
                 }
             }
+
+            pub fn children(&self) -> Vec<&Expr> {
+                match self {
+                    $( Expr::$i(expr) => expr.children(), )*
+                }
+            }
+
+            pub fn children_mut(&mut self) -> Vec<&mut Expr> {
+                match self {
+                    $( Expr::$i(expr) => expr.children_mut(), )*
+                }
+            }
         }
 
         impl Display for Expr {
@@ -50,6 +84,24 @@ macro_rules! expr_impl {
     };
 }
 
+impl Expr {
+    // aggregates returns every aggregate used within the expression, the
+    // arguments of an aggregate are not searched.
+    pub fn aggregates(&self) -> Vec<&AggregateExpression> {
+        let mut aggregates = Vec::new();
+        let mut stack = vec![self];
+
+        while let Some(expr) = stack.pop() {
+            match expr {
+                Expr::AggregateExpression(agg) => aggregates.push(agg),
+                expr => stack.extend(expr.children().into_iter().rev()),
+            }
+        }
+
+        aggregates
+    }
+}
+
 impl<'a> TryFrom<&Parser<'a>> for Expr {
     type Error = crate::Error;
     fn try_from(value: &Parser<'a>) -> std::result::Result<Self, Self::Error> {
@@ -57,6 +109,13 @@ impl<'a> TryFrom<&Parser<'a>> for Expr {
     }
 }
 
+impl<'a> TryFrom<&Parser<'a>> for Box<Expr> {
+    type Error = crate::Error;
+    fn try_from(value: &Parser<'a>) -> std::result::Result<Self, Self::Error> {
+        value.expression().map(Box::new)
+    }
+}
+
 expr_impl!(
     StringLiteral,
     NumberLiteral,
@@ -70,16 +129,33 @@ expr_impl!(
     AddExpression,
     SubtractExpression,
     SubExpression,
-    ExponentExpression
+    ExponentExpression,
+    NegativeExpression,
+    PositiveExpression,
+    NotExpression,
+    PathExpression,
+    AggregateExpression,
+    ParameterExpression,
+    ToUpper
 );
 
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::parser::Parser;
+    use crate::{Error, Kind, Span, Str};
+    use serde::Deserialize;
     use serde_json::Value;
 
-    impl Container for Value {}
+    impl Container for Value {
+        fn value(&self) -> Any<'_> {
+            Any::deserialize(self).unwrap_or_default()
+        }
+
+        fn get(&self, key: &str) -> Option<Any<'_>> {
+            Any::deserialize(self.as_object()?.get(key)?).ok()
+        }
+    }
 
     macro_rules! assert_expression {
         ( $source:expr, $expr:expr, $expected:expr) => {
@@ -102,7 +178,7 @@ mod test {
         assert_expression!(r#"{}"#, "25/2", "12");
         assert_expression!(r#"{}"#, "25.0/2", "12.5");
         assert_expression!(r#"{}"#, "25.0-2", "23.0");
-        assert_expression!(r#"{}"#, "25.0^2", "625");
+        assert_expression!(r#"{}"#, "25.0^2", "625.0");
         assert_expression!(r#"{}"#, "25.0*2", "50.0");
         assert_expression!(r#"{}"#, "25.0*2", "50.0");
         assert_expression!(r#"{}"#, "34-66*11+(45^2)/10.0", "-489.5");
@@ -111,7 +187,248 @@ mod test {
         assert_expression!(r#"{}"#, "{'something': true}", r#"{"something":true}"#);
         assert_expression!(r#"{}"#, "['something', 12]", r#"["something",12]"#);
         assert_expression!(r#"{}"#, "to_upper('something')", r#""SOMETHING""#);
+        assert_expression!(r#"{}"#, r"'it\'s'", r#""it's""#);
+        assert_expression!(r#"{}"#, r"'a\\b\n\u{e9}'", r#""a\\b\né""#);
+        assert_expression!(r#"{"a b": {"c\"": 1}}"#, r#"`"a b"."c\""`"#, "1");
+        assert_expression!(r#"{"a": 5}"#, "a", "5");
+        assert_expression!(r#"{"a": 5}"#, "b", "null");
+        assert_expression!(r#"{"a": {"b": [1, 2, 3]}}"#, "`a.b[1]`", "2");
+        assert_expression!(r#"{"a": {"b": [1, 2, 3]}}"#, "a.b[1] * 2", "4");
+        assert_expression!(r#"{"a": {"b": [1, 2, 3]}}"#, "a.b[*]", "[1,2,3]");
+        assert_expression!(r#"{"a": {"b": [1, 2, 3]}}"#, "a.b[5]", "null");
+        assert_expression!(r#"{"a": {"key name": 5}}"#, r#"`a."key name"`"#, "5");
+        assert_expression!(r#"{"a": {"key name": 5}}"#, r#"a."key name""#, "5");
+        assert_expression!(r#"{"a": [{"b": 1}, {"b": 2}]}"#, "`a.*.b`", "[1,2]");
+        assert_expression!(r#"[{"b": 1}, {"b": 2}]"#, "`*.b`", "[1,2]");
+        assert_expression!(r#"[[1, 2], [3]]"#, "`[1][0]`", "3");
+        assert_expression!(
+            r#"{"name": "x", "c": {"name": "y"}}"#,
+            "`~.name`",
+            r#"["x","y"]"#
+        );
+        assert_expression!(r#"{"a": "b"}"#, "to_upper(a)", r#""B""#);
+        assert_expression!(r#"{"a": 5}"#, "-a", "-5");
+        assert_expression!(r#"{"a": 5}"#, "- a * 2", "-10");
+        assert_expression!(r#"{"a": 5}"#, "+a", "5");
+        assert_expression!(r#"{}"#, "-2.5", "-2.5");
+        assert_expression!(r#"{}"#, "-(1 + 2)", "-3");
+        assert_expression!(r#"{}"#, "1 - -1", "2");
+        assert_expression!(r#"{}"#, "2 * -3", "-6");
+        assert_expression!(r#"{}"#, "-2 ^ 2", "-4");
+        assert_expression!(r#"{}"#, "(-2) ^ 2", "4");
+        assert_expression!(r#"{}"#, "2 ^ 3 ^ 2", "512");
+        assert_expression!(r#"{}"#, "(2 ^ 3) ^ 2", "64");
+        assert_expression!(r#"{}"#, "2.5 ^ 2", "6.25");
+        assert_expression!(r#"{}"#, "2 ^ -1", "0.5");
+        assert_expression!(r#"{}"#, "4 ^ 0.5", "2.0");
+        assert_expression!(r#"{}"#, "2 ^ 62", "4611686018427387904");
+        assert_expression!(r#"{}"#, "18446744073709551615 ^ 1", "18446744073709551615");
+        assert_expression!(r#"{}"#, "(-1) ^ 10000000001", "-1");
+        assert_expression!(r#"{}"#, "1 ^ 10000000000", "1");
+        assert_expression!(r#"{}"#, "-9223372036854775808", "-9223372036854775808");
+        assert_expression!(r#"{"a": false}"#, "!a", "true");
+        assert_expression!(r#"{}"#, "NOT true", "false");
+        assert_expression!(r#"{}"#, "not null", "true");
 
         Ok(())
     }
+
+    macro_rules! assert_display {
+        ( $expr:expr, $expected:expr ) => {
+            let expr = Expr::from($expr);
+            assert_eq!(expr.to_string(), $expected);
+            let source = expr.to_string();
+            let parsed = Parser::from(source.as_str()).expression()?;
+            assert_eq!(parsed.to_string(), $expected);
+            assert_eq!(parsed.evaluate(&Any::Null), expr.evaluate(&Any::Null));
+        };
+    }
+
+    #[test]
+    fn test_expression_display() -> Result<()> {
+        let parse = |source: &str| Parser::from(source).expression();
+
+        assert_display!(parse("null")?, "NULL");
+        assert_display!(parse("1 + 2 * 3")?, "1 + 2 * 3");
+        assert_display!(parse("(1 + 2) * 3")?, "(1 + 2) * 3");
+        assert_display!(parse("2 ^ 3 ^ 2")?, "2 ^ 3 ^ 2");
+        assert_display!(parse("(2 ^ 3) ^ 2")?, "(2 ^ 3) ^ 2");
+        assert_display!(parse("2 ^ -a ^ 2")?, "2 ^ -`a` ^ 2");
+        assert_display!(
+            parse("{'b': [1, 2.5], 'a\\'': {}}")?,
+            r"{'a\'': {}, 'b': [1, 2.5]}"
+        );
+        assert_display!(parse("[]")?, "[]");
+        assert_display!(parse("to_upper('a' )")?, "TO_UPPER('a')");
+        assert_display!(parse("count(*) + sum(a)")?, "COUNT(*) + SUM(`a`)");
+        assert_display!(parse("18446744073709551615")?, "18446744073709551615");
+        assert_display!(NumberLiteral::from(3.0), "3.0");
+        assert_display!(
+            ExponentExpression::new(
+                Expr::from(NumberLiteral::from(f64::NEG_INFINITY)),
+                Expr::from(NumberLiteral::from(f64::INFINITY))
+            ),
+            "(-1.0 / 0.0) ^ (1.0 / 0.0)"
+        );
+        assert_display!(parse("-a ^ 2")?, "-`a` ^ 2");
+        assert_display!(parse("(-a)^2")?, "(-`a`) ^ 2");
+        assert_display!(parse("- -a * +b")?, "-(-`a`) * +`b`");
+        assert_display!(parse("! NOT a")?, "NOT (NOT `a`)");
+        assert_display!(parse("-(1 + 2)")?, "-(1 + 2)");
+        assert_display!(
+            ExponentExpression::new(
+                Expr::from(NumberLiteral::from(-2)),
+                Expr::from(NumberLiteral::from(2))
+            ),
+            "(-2) ^ 2"
+        );
+        assert_display!(
+            NegativeExpression::new(Expr::from(NumberLiteral::from(-2.5))),
+            "-(-2.5)"
+        );
+        assert_display!(
+            MultiplyExpression::new(
+                Expr::from(AddExpression::new(
+                    Expr::from(NumberLiteral::from(1)),
+                    Expr::from(NumberLiteral::from(2))
+                )),
+                Expr::from(NumberLiteral::from(3))
+            ),
+            "(1 + 2) * 3"
+        );
+        assert_display!(
+            SubtractExpression::new(
+                Expr::from(NumberLiteral::from(1)),
+                Expr::from(SubtractExpression::new(
+                    Expr::from(NumberLiteral::from(2)),
+                    Expr::from(NumberLiteral::from(3))
+                ))
+            ),
+            "1 - (2 - 3)"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expression_container() -> Result<()> {
+        let d: Value = serde_json::from_str(r#"{"a": {"b": [1, 2, 3]}, "c": [{"d": 4}]}"#).unwrap();
+
+        let expr = Parser::from("a.b[2] + 1").expression()?;
+        assert_eq!(expr.evaluate(&d)?, Any::from(4));
+
+        let expr = Parser::from("`~.d`").expression()?;
+        assert_eq!(expr.evaluate(&d)?, Any::from([Any::from(4)]));
+
+        let root: Value = serde_json::from_str(r#"{"a": [1]}"#).unwrap();
+        let expr = Parser::from("`~`").expression()?;
+        assert_eq!(
+            expr.evaluate(&root)?,
+            Any::from([root.value(), Any::from([Any::from(1)]), Any::from(1)])
+        );
+
+        assert_eq!(d.kind(), Kind::Map);
+        assert_eq!(d.len(), 2);
+        assert_eq!(d.get("c").unwrap().kind(), Kind::List);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expression_errors() {
+        assert!(Parser::from("").expression().is_err());
+        assert!(Parser::from(")").expression().is_err());
+        assert!(Parser::from("``").expression().is_err());
+        assert!(Parser::from("`a.`").expression().is_err());
+        assert!(Parser::from("`a[b]`").expression().is_err());
+        assert!(Parser::from("unknown(1)").expression().is_err());
+    }
+
+    macro_rules! assert_error {
+        ( $expr:expr, $expected:expr ) => {
+            let d = Any::from([
+                (Str::from("a"), Any::from("x")),
+                (Str::from("n"), Any::from(5)),
+                (Str::from("zero"), Any::from(0)),
+                (Str::from("max"), Any::from(i64::MAX)),
+            ]);
+            let err = Parser::from($expr)
+                .expression()
+                .and_then(|expr| expr.evaluate(&d).map(Any::into_owned))
+                .unwrap_err();
+            assert_eq!(err, $expected, "{}", $expr);
+        };
+    }
+
+    #[test]
+    fn test_expression_error_kinds() {
+        assert_error!("a + 1", Error::type_mismatch(Kind::Number, Kind::Str));
+        assert_error!("1 - true", Error::type_mismatch(Kind::Number, Kind::Bool));
+        assert_error!(
+            "SUM(a)",
+            Error::ExpressionError(String::from(
+                "SUM(`a`) can only be used once documents are aggregated"
+            ))
+        );
+        assert_error!(
+            "unknown(1)",
+            Error::UnknownFunction {
+                name: String::from("unknown"),
+                span: Span::new("unknown(1)", 0..7)
+            }
+        );
+        assert_error!(
+            "n / zero",
+            Error::Arithmetic(String::from("`n` / `zero` divides by zero"))
+        );
+        assert_error!(
+            "max + n",
+            Error::Arithmetic(String::from("`max` + `n` overflows"))
+        );
+        assert_error!("-a", Error::type_mismatch(Kind::Number, Kind::Str));
+        assert_error!("NOT n", Error::type_mismatch(Kind::Bool, Kind::Number));
+        assert_error!(
+            "-(-9223372036854775808)",
+            Error::Arithmetic(String::from("-(-9223372036854775808) overflows"))
+        );
+        assert_error!(
+            "n ^ 30",
+            Error::Arithmetic(String::from("`n` ^ 30 overflows"))
+        );
+        assert_error!(
+            "10.0 ^ 400",
+            Error::Arithmetic(String::from("10.0 ^ 400 overflows"))
+        );
+        assert_error!(
+            "0 ^ -1",
+            Error::Arithmetic(String::from("0 ^ -1 divides by zero"))
+        );
+        assert_error!(
+            "0.0 ^ -0.5",
+            Error::Arithmetic(String::from("0.0 ^ -0.5 divides by zero"))
+        );
+        assert_error!("a ^ 2", Error::type_mismatch(Kind::Number, Kind::Str));
+        assert_error!(
+            "1 +",
+            Error::UnexpectedEOF {
+                span: Span::new("1 +", 3..3)
+            }
+        );
+        assert_error!(
+            "`a[b]`",
+            Error::Parse {
+                message: String::from("invalid index [b] in path a[b]"),
+                span: Span::new("`a[b]`", 1..5),
+                hint: None,
+            }
+        );
+        assert_error!(
+            "a.b[c]",
+            Error::Parse {
+                message: String::from("invalid index [c] in path a.b[c]"),
+                span: Span::new("a.b[c]", 0..6),
+                hint: None,
+            }
+        );
+    }
 }
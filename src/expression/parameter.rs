@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use crate::{
+    Any, Container, Error, Parser, Result, TokenKind,
+    parser::{PARAM_NAMED, PARAM_POSITIONAL, consume, must_token},
+};
+
+use super::Expression;
+
+// Parameter names a value which is bound to a query when it is run, rather
+// than being written into the query. Positional parameters are written like
+// `$1` and start at 1, named parameters are written like `:name`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Parameter {
+    Positional(usize),
+    Named(String),
+}
+
+impl Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Parameter::Positional(index) => write!(f, "{}{}", PARAM_POSITIONAL, index),
+            Parameter::Named(name) => write!(f, "{}{}", PARAM_NAMED, name),
+        }
+    }
+}
+
+impl From<usize> for Parameter {
+    fn from(index: usize) -> Self {
+        Parameter::Positional(index)
+    }
+}
+
+impl From<&str> for Parameter {
+    fn from(name: &str) -> Self {
+        Parameter::Named(String::from(name))
+    }
+}
+
+impl From<String> for Parameter {
+    fn from(name: String) -> Self {
+        Parameter::Named(name)
+    }
+}
+
+// ParameterExpression returns the value bound to a parameter. Values are
+// looked up in the container the expression is evaluated against, see
+// Params for binding values. Evaluating a parameter which was not bound is
+// an error.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParameterExpression {
+    parameter: Parameter,
+}
+
+impl ParameterExpression {
+    pub fn new(parameter: Parameter) -> Self {
+        Self { parameter }
+    }
+
+    pub fn parameter(&self) -> &Parameter {
+        &self.parameter
+    }
+}
+
+impl Expression for ParameterExpression {
+    fn evaluate<'a: 'b, 'b, T: Container>(&'a self, c: &'b T) -> Result<Any<'b>> {
+        c.parameter(&self.parameter)
+            .ok_or_else(|| Error::UnboundParameter {
+                parameter: self.parameter.clone(),
+            })
+    }
+}
+
+impl Display for ParameterExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.parameter)
+    }
+}
+
+impl<'a> Parser<'a> {
+    // parameter_expression parses a positional parameter like `$1`, or a
+    // named parameter like `:name`. The name must directly follow the `:`.
+    pub(crate) fn parameter_expression(&self) -> Result<ParameterExpression> {
+        let tok = must_token!(self)?;
+        if tok.kind() == TokenKind::Parameter {
+            return tok.text()[PARAM_POSITIONAL.len()..]
+                .parse::<usize>()
+                .ok()
+                .filter(|index| *index > 0)
+                .map(|index| ParameterExpression::new(Parameter::Positional(index)))
+                .ok_or_else(|| {
+                    Error::with_history(&format!("invalid parameter \"{}\"", tok), self.history())
+                        .hint("positional parameters are numbered from 1, like $1")
+                });
+        }
+
+        let adjacent = self.adjacent();
+        match self.peak() {
+            Some(name)
+                if adjacent
+                    && name.wrap().is_none()
+                    && matches!(name.kind(), TokenKind::Identifier | TokenKind::Keyword) =>
+            {
+                consume!(self);
+                Ok(ParameterExpression::new(Parameter::Named(String::from(
+                    name.text(),
+                ))))
+            }
+            _ => Err(Error::with_history(
+                &format!("expected a parameter name after \"{}\"", PARAM_NAMED),
+                self.history(),
+            )
+            .hint("named parameters are written like :name")),
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use crate::{
+    Any, Container, Error, Result,
+    lexor::{escape, unescape_next},
+    parser::{ARRAY_WRAP, ARRAY_WRAP_END, IDENTIFIER_WRAP, KEY_WRAP},
+};
+
+use super::Expression;
+
+const PATH_SEP: char = '.';
+const PATH_INDEX_OPEN: char = '[';
+const PATH_INDEX_CLOSE: char = ']';
+const PATH_QUOTE: char = '"';
+const PATH_ESCAPE: char = '\\';
+const PATH_WILDCARD: char = '*';
+const PATH_RECURSIVE: char = '~';
+// PATH_ESCAPED are escaped within quoted keys, the tick would otherwise end
+// the KEY_WRAP the path is written in
+const PATH_ESCAPED: &str = "\"`";
+
+// Segment is a single step within a path
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Segment {
+    // Key selects the child stored under the key of a map
+    Key(String),
+    // Index selects the item stored at the index of a list
+    Index(usize),
+    // Wildcard selects every child of a map or list
+    Wildcard,
+    // Recursive selects the current value and all of its descendants
+    Recursive,
+}
+
+impl Display for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Segment::Key(key) => {
+                if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    return write!(f, "{}", key);
+                }
+
+                write!(
+                    f,
+                    "{}{}{}",
+                    PATH_QUOTE,
+                    escape(key, PATH_ESCAPED),
+                    PATH_QUOTE
+                )
+            }
+            Segment::Index(index) => write!(f, "{}{}{}", PATH_INDEX_OPEN, index, PATH_INDEX_CLOSE),
+            Segment::Wildcard => write!(f, "{}", PATH_WILDCARD),
+            Segment::Recursive => write!(f, "{}", PATH_RECURSIVE),
+        }
+    }
+}
+
+// PathExpression references data within the container the expression is
+// evaluated against. Paths are made up of segments separated by `.`, for
+// instance:
+//
+// `a.b[0]."key name".*.c`
+// `~.name`
+//
+// When the path contains a wildcard or recursive segment a list of every
+// match is returned, otherwise the single value is returned. Paths which
+// don't match anything return NULL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathExpression {
+    segments: Vec<Segment>,
+}
+
+impl PathExpression {
+    pub fn new(segments: Vec<Segment>) -> Self {
+        PathExpression { segments }
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    // is_multiple returns true when the path can match more than one value
+    pub fn is_multiple(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|s| matches!(s, Segment::Wildcard | Segment::Recursive))
+    }
+
+    // path returns the path without being wrapped, this is what results are
+    // named after when the path is selected
+    pub fn path(&self) -> String {
+        let mut path = String::new();
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 && !matches!(segment, Segment::Index(_)) {
+                path.push(PATH_SEP);
+            }
+            path.push_str(&segment.to_string());
+        }
+        path
+    }
+}
+
+impl Expression for PathExpression {
+    fn evaluate<'a: 'b, 'b, T: Container>(&'a self, c: &'b T) -> Result<Any<'b>> {
+        let mut matches = select_container(c, &self.segments);
+
+        if self.is_multiple() {
+            Ok(Any::List(matches))
+        } else {
+            Ok(matches.pop().unwrap_or_default())
+        }
+    }
+}
+
+// select_container applies the segments to the container. Only the data
+// referenced by the first segment is pulled out of the container, the rest
+// of the path is applied to that.
+fn select_container<'b, T: Container>(c: &'b T, segments: &[Segment]) -> Vec<Any<'b>> {
+    let Some((first, rest)) = segments.split_first() else {
+        return Vec::new();
+    };
+
+    let select = |v: Any<'b>| {
+        if rest.is_empty() {
+            vec![v]
+        } else {
+            select_any(&v, rest)
+        }
+    };
+
+    match first {
+        Segment::Key(key) => c.get(key).map(select).unwrap_or_default(),
+        Segment::Index(index) => c.index(*index).map(select).unwrap_or_default(),
+        Segment::Wildcard => c.children().flat_map(select).collect(),
+        Segment::Recursive => {
+            let mut matches = if rest.is_empty() {
+                vec![c.value()]
+            } else {
+                select_container(c, rest)
+            };
+            for child in c.children() {
+                matches.extend(select_any(&child, segments));
+            }
+            matches
+        }
+    }
+}
+
+// select_any applies the segments to a value, copying out every match
+fn select_any<'b>(v: &Any<'b>, segments: &[Segment]) -> Vec<Any<'b>> {
+    let Some((first, rest)) = segments.split_first() else {
+        return vec![v.clone()];
+    };
+
+    match (first, v) {
+        (Segment::Key(key), Any::Map(map)) => map
+            .get(key.as_str())
+            .map(|v| select_any(v, rest))
+            .unwrap_or_default(),
+        (Segment::Index(index), Any::List(list)) => list
+            .get(*index)
+            .map(|v| select_any(v, rest))
+            .unwrap_or_default(),
+        (Segment::Wildcard, Any::List(list)) => {
+            list.iter().flat_map(|v| select_any(v, rest)).collect()
+        }
+        (Segment::Wildcard, Any::Map(map)) => {
+            map.values().flat_map(|v| select_any(v, rest)).collect()
+        }
+        (Segment::Recursive, _) => {
+            let mut matches = select_any(v, rest);
+            let children: Box<dyn Iterator<Item = &Any<'b>>> = match v {
+                Any::List(list) => Box::new(list.iter()),
+                Any::Map(map) => Box::new(map.values()),
+                _ => Box::new(std::iter::empty()),
+            };
+
+            for child in children {
+                matches.extend(select_any(child, segments));
+            }
+            matches
+        }
+        _ => Vec::new(),
+    }
+}
+
+impl Display for PathExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}{}", KEY_WRAP, self.path(), KEY_WRAP)
+    }
+}
+
+impl TryFrom<&str> for PathExpression {
+    type Error = Error;
+
+    // try_from parses the path syntax, without the wrapping KEY_WRAP tokens
+    fn try_from(path: &str) -> std::result::Result<Self, Self::Error> {
+        let mut segments = Vec::new();
+        let mut chars = path.chars().peekable();
+
+        // expect_segment is true when a segment must follow, at the start of
+        // the path and after each separator.
+        let mut expect_segment = true;
+        while let Some(c) = chars.next() {
+            match c {
+                PATH_SEP if !expect_segment => expect_segment = true,
+                PATH_INDEX_OPEN => {
+                    let mut index = String::new();
+                    for c in chars.by_ref() {
+                        if c == PATH_INDEX_CLOSE {
+                            break;
+                        }
+                        index.push(c);
+                    }
+
+                    if index == PATH_WILDCARD.to_string() {
+                        segments.push(Segment::Wildcard);
+                    } else {
+                        segments.push(Segment::Index(index.trim().parse().map_err(|_| {
+                            Error::InvalidQuery(format!(
+                                "invalid index {}{}{} in path {}",
+                                ARRAY_WRAP, index, ARRAY_WRAP_END, path
+                            ))
+                        })?));
+                    }
+                    expect_segment = false;
+                }
+                PATH_QUOTE if expect_segment => {
+                    let mut key = String::new();
+                    let mut closed = false;
+                    while let Some(c) = chars.next() {
+                        match c {
+                            PATH_ESCAPE => key.push(unescape_next(&mut chars)?),
+                            PATH_QUOTE => {
+                                closed = true;
+                                break;
+                            }
+                            c => key.push(c),
+                        }
+                    }
+
+                    if !closed {
+                        return Err(Error::InvalidQuery(format!(
+                            "missing closing {} in path {}",
+                            IDENTIFIER_WRAP, path
+                        )));
+                    }
+
+                    segments.push(Segment::Key(key));
+                    expect_segment = false;
+                }
+                PATH_WILDCARD if expect_segment => {
+                    segments.push(Segment::Wildcard);
+                    expect_segment = false;
+                }
+                PATH_RECURSIVE if expect_segment => {
+                    segments.push(Segment::Recursive);
+                    expect_segment = false;
+                }
+                c if expect_segment => {
+                    let mut key = String::new();
+                    let mut next = Some(c);
+                    while let Some(c) = next {
+                        match c {
+                            // the separators can also be escaped in keys
+                            // which are not quoted, like `a\.b`
+                            PATH_ESCAPE => {
+                                match chars.next_if(|c| *c == PATH_SEP || *c == PATH_INDEX_OPEN) {
+                                    Some(c) => key.push(c),
+                                    None => key.push(unescape_next(&mut chars)?),
+                                }
+                            }
+                            c => key.push(c),
+                        }
+
+                        next = chars.next_if(|c| *c != PATH_SEP && *c != PATH_INDEX_OPEN);
+                    }
+
+                    segments.push(Segment::Key(key));
+                    expect_segment = false;
+                }
+                c => {
+                    return Err(Error::InvalidQuery(format!(
+                        "unexpected \"{}\" in path {}",
+                        c, path
+                    )));
+                }
+            }
+        }
+
+        if expect_segment {
+            return Err(Error::InvalidQuery(format!(
+                "expected key at the end of path \"{}\"",
+                path
+            )));
+        }
+
+        Ok(PathExpression::new(segments))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    macro_rules! assert_path {
+        ( $path:expr, $expected:expr ) => {
+            let path = PathExpression::try_from($path)?;
+            assert_eq!(path.to_string(), $expected);
+        };
+    }
+
+    #[test]
+    fn test_path() -> Result<()> {
+        assert_path!("a", "`a`");
+        assert_path!("a.b[0].c", "`a.b[0].c`");
+        assert_path!("[0][1]", "`[0][1]`");
+        assert_path!("a[*]", "`a.*`");
+        assert_path!("*.b", "`*.b`");
+        assert_path!("~.name", "`~.name`");
+        assert_path!(r#"a."key name".b"#, r#"`a."key name".b`"#);
+        assert_path!(r#""say \"hi\"""#, r#"`"say \"hi\""`"#);
+        assert_path!(r#"a\.b"#, r#"`"a.b"`"#);
+        assert_path!(r#"a\[0]"#, r#"`"a[0]"`"#);
+        assert!(PathExpression::try_from(r#""a\.b""#).is_err());
+        assert_path!(r#""tab\there\u{e9}".b"#, r#"`"tab\thereé".b`"#);
+        assert_path!(r#""tick\`""#, r#"`"tick\`"`"#);
+        assert!(PathExpression::try_from(r#""bad \u{zz}""#).is_err());
+
+        let path = PathExpression::try_from("a.*[2]")?;
+        assert_eq!(
+            path.segments(),
+            &[
+                Segment::Key(String::from("a")),
+                Segment::Wildcard,
+                Segment::Index(2)
+            ]
+        );
+
+        Ok(())
+    }
+}
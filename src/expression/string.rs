@@ -1,11 +1,20 @@
 use super::{Expr, Expression};
 use crate::Any;
 use dql_derive::Function;
+use serde::{Deserialize, Serialize};
 
-#[derive(Function, Clone, Debug)]
+#[derive(Function, Clone, Debug, Serialize, Deserialize)]
 #[function(name = "to_upper")]
 pub struct ToUpper {
-    value: Expr,
+    value: Box<Expr>,
+}
+
+impl ToUpper {
+    pub fn new(value: Expr) -> Self {
+        ToUpper {
+            value: Box::new(value),
+        }
+    }
 }
 
 impl Expression for ToUpper {
@@ -16,4 +25,12 @@ impl Expression for ToUpper {
         let v = self.value.evaluate(c)?;
         Ok(Any::from(v.as_str()?.to_uppercase()))
     }
+
+    fn children(&self) -> Vec<&Expr> {
+        vec![&self.value]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Expr> {
+        vec![&mut self.value]
+    }
 }
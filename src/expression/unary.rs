@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use crate::{
+    Any, Container, Error, Expr, Number, Result,
+    condition::must_bool,
+    parser::{ADD, MINUS, NOT, SUB_EXPR_CLOSE, SUB_EXPR_OPEN},
+};
+
+use super::{Expression, PRECEDENCE_UNARY, precedence};
+
+// Unary expressions apply a prefix operator to the expression that follows
+// it. The operators bind looser than `^` and tighter than everything else,
+// so `-a ^ 2` is `-(a ^ 2)`.
+macro_rules! impl_expression_unary_op {
+    ($name:ident, $op:ident, $sep:expr, $apply:ident) => {
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        pub struct $name {
+            expr: Box<Expr>,
+        }
+
+        impl $name {
+            pub fn new(expr: Expr) -> Self {
+                Self {
+                    expr: Box::new(expr),
+                }
+            }
+        }
+
+        impl Expression for $name {
+            fn evaluate<'a: 'b, 'b, T: Container>(&'a self, c: &'b T) -> Result<Any<'b>> {
+                $apply(self, self.expr.evaluate(c)?)
+            }
+
+            fn children(&self) -> Vec<&Expr> {
+                vec![&self.expr]
+            }
+
+            fn children_mut(&mut self) -> Vec<&mut Expr> {
+                vec![&mut self.expr]
+            }
+        }
+
+        // the operand is wrapped when it is also a unary expression, so
+        // `-(-a)` isn't written as the line comment `--a`
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                if precedence(&self.expr) <= PRECEDENCE_UNARY {
+                    write!(
+                        f,
+                        "{}{}{}{}{}",
+                        $op, $sep, SUB_EXPR_OPEN, self.expr, SUB_EXPR_CLOSE
+                    )
+                } else {
+                    write!(f, "{}{}{}", $op, $sep, self.expr)
+                }
+            }
+        }
+    };
+}
+
+impl_expression_unary_op!(NegativeExpression, MINUS, "", negative);
+impl_expression_unary_op!(PositiveExpression, ADD, "", positive);
+impl_expression_unary_op!(NotExpression, NOT, " ", not);
+
+// negative negates a number, negating the smallest integer overflows
+fn negative<'b>(expr: &NegativeExpression, value: Any<'b>) -> Result<Any<'b>> {
+    let value: Number = value.try_into()?;
+    value
+        .checked_neg()
+        .map(Any::Number)
+        .ok_or_else(|| Error::Arithmetic(format!("{} overflows", expr)))
+}
+
+// positive returns the number as is, it is an error for anything other
+// than a number
+fn positive<'b>(_: &PositiveExpression, value: Any<'b>) -> Result<Any<'b>> {
+    let value: Number = value.try_into()?;
+    Ok(Any::Number(value))
+}
+
+// not negates a bool, null is treated as false like it is in conditions
+fn not<'b>(_: &NotExpression, value: Any<'b>) -> Result<Any<'b>> {
+    Ok(Any::Bool(!must_bool(&value)?))
+}
@@ -0,0 +1,364 @@
+use std::{fmt::Display, time::Duration};
+
+use crate::{
+    Condition, Error, Parser, Query, Result, TokenKind,
+    condition::{PRECEDENCE_AND, PRECEDENCE_OR, precedence, write_operand},
+    lexor::Lexer,
+    parser::{
+        AND, BY, EMIT, EVICT, FROM, FROM_SEP, GROUP, HAVING, INTERVAL, LIMIT, OFFSET, ON, OR,
+        ORDER, SELECT, SELECT_SEP, WHERE,
+    },
+    query::{write_duration, write_identifier},
+};
+
+const DEFAULT_WIDTH: usize = 80;
+const DEFAULT_INDENT: usize = 2;
+
+// FormatOptions controls how format lays out a query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    width: usize,
+    indent: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            width: DEFAULT_WIDTH,
+            indent: DEFAULT_INDENT,
+        }
+    }
+}
+
+impl FormatOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // width sets the width lines are wrapped at. Lines are only broken
+    // between list items and conditions, a single expression wider than
+    // the width is left on one line.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    // indent sets the number of spaces wrapped lines are indented by
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+}
+
+// format parses the query and lays it out in the canonical style, see
+// Query::format. The query is laid out from its AST which has no comments,
+// so queries with comments return an error rather than losing them.
+pub fn format(source: &str) -> Result<String> {
+    format_with(source, &FormatOptions::default())
+}
+
+// format_with parses the query and lays it out with the given options
+pub fn format_with(source: &str, options: &FormatOptions) -> Result<String> {
+    let comment = Lexer::from(source)
+        .keep_comments(true)
+        .tokens()
+        .find(|tok| tok.kind() == TokenKind::Comment);
+    if let Some(comment) = comment {
+        return Err(Error::Parse {
+            message: String::from("comments would be lost by formatting"),
+            span: comment.span(source),
+            hint: Some(String::from("remove the comments to format the query")),
+        });
+    }
+
+    Ok(Parser::from(source).query()?.format(options))
+}
+
+impl Query {
+    // format lays the query out in the canonical style. Every clause starts
+    // on its own line with upper case keywords. When a clause is wider than
+    // the width, lists are split one item per line and conditions are split
+    // before each AND and OR, the wrapped lines are indented:
+    //
+    // SELECT
+    //   host,
+    //   COUNT(*) AS total
+    // FROM events
+    // WHERE `status` = 500
+    //   AND `path` != '/health'
+    // GROUP BY `host`
+    pub fn format(&self, options: &FormatOptions) -> String {
+        let mut layout = Layout::new(options);
+
+        layout.list(SELECT, SELECT_SEP, self.projections());
+        layout.list(
+            FROM,
+            FROM_SEP,
+            &self
+                .from()
+                .iter()
+                .map(|ns| Identifier(ns))
+                .collect::<Vec<_>>(),
+        );
+        layout.condition(WHERE, self.filter());
+        layout.list(&format!("{} {}", GROUP, BY), SELECT_SEP, self.group());
+        layout.condition(HAVING, self.having());
+        layout.duration(INTERVAL, self.interval());
+        layout.duration(EVICT, self.evict());
+        layout.condition(&format!("{} {}", EMIT, ON), self.emit());
+        layout.list(&format!("{} {}", ORDER, BY), SELECT_SEP, self.order());
+
+        if let Some(limit) = self.limit() {
+            layout.line(format!("{} {}", LIMIT, limit));
+        }
+
+        if let Some(offset) = self.offset() {
+            layout.line(format!("{} {}", OFFSET, offset));
+        }
+
+        layout.lines.join("\n")
+    }
+}
+
+// Layout collects the lines of a formatted query
+struct Layout<'o> {
+    options: &'o FormatOptions,
+    lines: Vec<String>,
+}
+
+impl<'o> Layout<'o> {
+    fn new(options: &'o FormatOptions) -> Self {
+        Layout {
+            options,
+            lines: Vec::new(),
+        }
+    }
+
+    fn fits(&self, line: &str) -> bool {
+        line.chars().count() <= self.options.width
+    }
+
+    fn line(&mut self, line: String) {
+        self.lines.push(line);
+    }
+
+    fn indented(&mut self, line: String) {
+        self.lines
+            .push(format!("{}{}", " ".repeat(self.options.indent), line));
+    }
+
+    // list writes the clause on one line when it fits, otherwise the
+    // keyword is followed by one item per line
+    fn list<T: Display>(&mut self, keyword: &str, sep: &str, items: &[T]) {
+        if items.is_empty() {
+            return;
+        }
+
+        let items = items.iter().map(|i| i.to_string()).collect::<Vec<_>>();
+        let line = format!("{} {}", keyword, items.join(&format!("{} ", sep)));
+        if items.len() == 1 || self.fits(&line) {
+            return self.line(line);
+        }
+
+        self.line(String::from(keyword));
+        let last = items.len() - 1;
+        for (i, item) in items.into_iter().enumerate() {
+            if i < last {
+                self.indented(format!("{}{}", item, sep));
+            } else {
+                self.indented(item);
+            }
+        }
+    }
+
+    // condition writes the clause on one line when it fits, otherwise the
+    // line is broken before each AND and OR which isn't wrapped in
+    // parenthesis
+    fn condition(&mut self, keyword: &str, cond: Option<&Condition>) {
+        let Some(cond) = cond else {
+            return;
+        };
+
+        let line = format!("{} {}", keyword, cond);
+        if self.fits(&line) {
+            return self.line(line);
+        }
+
+        let mut parts = Vec::new();
+        flatten(cond, &mut parts);
+        for (op, cond) in parts {
+            match op {
+                Some(op) => self.indented(format!("{} {}", op, cond)),
+                None => self.line(format!("{} {}", keyword, cond)),
+            }
+        }
+    }
+
+    fn duration(&mut self, keyword: &str, duration: Option<Duration>) {
+        if let Some(duration) = duration {
+            self.line(format!("{} {}", keyword, DurationLiteral(duration)));
+        }
+    }
+}
+
+// flatten splits a condition at each AND and OR which isn't wrapped in
+// parenthesis, keeping the operator which comes before each part. The parts
+// are in the same order they are written in, so joining them gives back the
+// condition.
+fn flatten(cond: &Condition, parts: &mut Vec<(Option<&'static str>, String)>) {
+    let (op, op_precedence) = match cond {
+        Condition::AndCondition(_) => (AND, PRECEDENCE_AND),
+        Condition::OrCondition(_) => (OR, PRECEDENCE_OR),
+        _ => return parts.push((None, cond.to_string())),
+    };
+
+    // the same as Display, the right side is wrapped when it has the same
+    // precedence
+    let children = cond.conditions();
+    flatten_operand(children[0], precedence(children[0]) < op_precedence, parts);
+    let right = parts.len();
+    flatten_operand(children[1], precedence(children[1]) <= op_precedence, parts);
+    parts[right].0 = Some(op);
+}
+
+fn flatten_operand(cond: &Condition, wrap: bool, parts: &mut Vec<(Option<&'static str>, String)>) {
+    if !wrap {
+        return flatten(cond, parts);
+    }
+
+    parts.push((None, Wrapped(cond).to_string()));
+}
+
+struct Wrapped<'a>(&'a Condition);
+
+impl Display for Wrapped<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_operand(f, self.0, true)
+    }
+}
+
+struct Identifier<'a>(&'a str);
+
+impl Display for Identifier<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_identifier(f, self.0)
+    }
+}
+
+struct DurationLiteral(Duration);
+
+impl Display for DurationLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_duration(f, self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{AndCondition, OrCondition, QueryBuilder, Span, col};
+
+    macro_rules! assert_format {
+        ( $query:expr, $width:expr, $expected:expr ) => {
+            let options = FormatOptions::new().width($width);
+            let formatted = format_with($query, &options)?;
+            assert_eq!(formatted, $expected);
+            assert_eq!(format_with(&formatted, &options)?, formatted);
+            assert_eq!(
+                Parser::from(formatted.as_str()).query()?.to_string(),
+                Parser::from($query).query()?.to_string()
+            );
+        };
+    }
+
+    #[test]
+    fn test_format() -> Result<()> {
+        assert_format!(
+            "select * from events where a = true limit 10 offset 5",
+            80,
+            "SELECT *\nFROM events\nWHERE `a` = TRUE\nLIMIT 10\nOFFSET 5"
+        );
+        assert_format!(
+            "select host, count(*) as total, to_upper(a) from events group by host",
+            30,
+            "SELECT\n  `host`,\n  COUNT(*) AS total,\n  TO_UPPER(`a`)\nFROM events\nGROUP BY `host`"
+        );
+        assert_format!(
+            "SELECT * FROM events WHERE a = 1 and (b = 2 or c = 3) or d",
+            30,
+            "SELECT *\nFROM events\nWHERE `a` = 1\n  AND (`b` = 2 OR `c` = 3)\n  OR `d`"
+        );
+        assert_format!(
+            "SELECT * FROM events WHERE (a = 1 or b = 2) and c = 3 and (d or e)",
+            30,
+            "SELECT *\nFROM events\nWHERE (`a` = 1 OR `b` = 2)\n  AND `c` = 3\n  AND (`d` OR `e`)"
+        );
+        assert_format!(
+            "SELECT {'b': 1, 'a': [1, 2]} FROM \"my events\" order by a desc, b",
+            80,
+            "SELECT {'a': [1, 2], 'b': 1}\nFROM \"my events\"\nORDER BY `a` DESC, `b` ASC"
+        );
+        assert_format!(
+            "SELECT a FROM events GROUP BY a INTERVAL '1m' EVICT '1h' EMIT ON count(*) > 10",
+            80,
+            "SELECT `a`\nFROM events\nGROUP BY `a`\nINTERVAL '60s'\nEVICT '3600s'\nEMIT ON COUNT(*) > 10"
+        );
+
+        let options = FormatOptions::new().width(10).indent(4);
+        assert_eq!(
+            format_with("SELECT a, b FROM events", &options)?,
+            "SELECT\n    `a`,\n    `b`\nFROM events"
+        );
+        assert_eq!(format("SELECT a FROM events")?, "SELECT `a`\nFROM events");
+        assert!(format("SELECT FROM").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_built() -> Result<()> {
+        // conditions built without SubCondition keep their meaning
+        let cond = |source: &str| Parser::from(source).condition();
+        let filter = Condition::from(AndCondition::new(
+            Condition::from(OrCondition::new(cond("a = 1")?, cond("b = 2")?)),
+            Condition::from(OrCondition::new(cond("c = 3")?, cond("d = 4")?)),
+        ));
+        let query = QueryBuilder::select([col("a")])
+            .from("events")
+            .filter(filter)
+            .build();
+        let formatted = query.format(&FormatOptions::new().width(30));
+        assert_eq!(
+            formatted,
+            "SELECT `a`\nFROM events\nWHERE (`a` = 1 OR `b` = 2)\n  AND (`c` = 3 OR `d` = 4)"
+        );
+        assert_eq!(
+            Parser::from(formatted.as_str()).query()?.to_string(),
+            query.to_string()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_comments() -> Result<()> {
+        let source = "SELECT a -- the host\nFROM events";
+        match format(source).unwrap_err() {
+            Error::Parse { span, hint, .. } => {
+                assert_eq!(span, Span::new(source, 9..20));
+                assert_eq!(
+                    hint.as_deref(),
+                    Some("remove the comments to format the query")
+                );
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+        assert!(format("SELECT a /* b */ FROM events").is_err());
+        assert_eq!(
+            format("SELECT '-- a' FROM events")?,
+            "SELECT '-- a'\nFROM events"
+        );
+
+        Ok(())
+    }
+}
@@ -1,3 +1,7 @@
+use std::{fmt::Display, ops::Range};
+
+use crate::{Error, Result, Span, parser::KEYWORDS};
+
 const TOKEN_KEY: char = '"';
 const TOKEN_STRING_LITERAL: char = '\'';
 const TOKEN_ESCAPE: char = '\\';
@@ -20,11 +24,134 @@ const TOKEN_MULTIPLY: char = '*';
 const TOKEN_MODULUS: char = '%';
 const TOKEN_EXPONENT: char = '^';
 const TOKEN_TICK: char = '`';
+const TOKEN_PARAMETER: char = '$';
+const TOKEN_LINE_COMMENT: &str = "--";
+const TOKEN_BLOCK_COMMENT: &str = "/*";
+const TOKEN_BLOCK_COMMENT_END: &str = "*/";
+
+const OPERATORS: [char; 10] = [
+    TOKEN_EQUAL,
+    TOKEN_NEGATE,
+    TOKEN_GREATER,
+    TOKEN_LESS,
+    TOKEN_PLUS,
+    TOKEN_MINUS,
+    TOKEN_DIVIDE,
+    TOKEN_MULTIPLY,
+    TOKEN_MODULUS,
+    TOKEN_EXPONENT,
+];
+
+const PUNCTUATION: [char; 8] = [
+    TOKEN_PARENTHESIS_OPEN,
+    TOKEN_PARENTHESIS_CLOSE,
+    TOKEN_CURLEY_OPEN,
+    TOKEN_CURLEY_CLOSE,
+    TOKEN_SQUARE_OPEN,
+    TOKEN_SQUARE_CLOSE,
+    TOKEN_COMMA,
+    TOKEN_COLON,
+];
+
+// anything wrapped in one of these is returned as a single token, the
+// wrapping character can be escaped inside with \
+const QUOTES: [char; 3] = [TOKEN_KEY, TOKEN_STRING_LITERAL, TOKEN_TICK];
+
+// TokenKind is what a token is, as far as the lexer can tell without
+// knowing where in a query the token is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    // Keyword is a reserved word like SELECT or AND, in any case
+    Keyword,
+    // Identifier is a name like a path, namespace or function. Identifiers
+    // wrapped in " or ` can contain anything, including keywords
+    Identifier,
+    // String is a string literal wrapped in '
+    String,
+    // Number is a word starting with a digit
+    Number,
+    // Operator is a math or comparison operator like + or >=
+    Operator,
+    // Punctuation is brackets and separators like ( or ,
+    Punctuation,
+    // Parameter is a positional parameter like $1, named parameters like
+    // :name are a `:` followed by an identifier
+    Parameter,
+    // Comment is a -- line comment or a /* block comment */, these are only
+    // returned when the lexer keeps comments
+    Comment,
+}
+
+// Token is a single token of a query. The text of a wrapped token doesn't
+// include the wrapping characters, though escape characters are still
+// present.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    kind: TokenKind,
+    text: &'a str,
+    wrap: Option<&'a str>,
+    closed: bool,
+    start: usize,
+    end: usize,
+}
+
+impl<'a> Token<'a> {
+    pub fn kind(&self) -> TokenKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+
+    // wrap returns the character the token was wrapped in, if any
+    pub fn wrap(&self) -> Option<&'a str> {
+        self.wrap
+    }
+
+    // closed returns false when a wrapped token or block comment runs to the
+    // end of the query without its closing characters
+    pub fn closed(&self) -> bool {
+        self.closed
+    }
+
+    // range returns the byte range of the token in the query, including any
+    // wrapping characters
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
 
+    // span returns where the token is in source
+    pub fn span(&self, source: &str) -> Span {
+        Span::new(source, self.range())
+    }
+
+    // is returns true when the token is the unwrapped word or symbol seen.
+    // This is not case sensitive, wrapped tokens never match. Identifiers
+    // never match a keyword, which happens when the lexer only reads upper
+    // case keywords and the keyword is written in lower case.
+    pub fn is(&self, seen: &str) -> bool {
+        self.wrap.is_none()
+            && self.text.eq_ignore_ascii_case(seen)
+            && !(self.kind == TokenKind::Identifier && is_keyword(seen, false))
+    }
+}
+
+impl Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let wrap = self.wrap.unwrap_or_default();
+        let end = if self.closed { wrap } else { "" };
+        write!(f, "{}{}{}", wrap, self.text, end)
+    }
+}
+
+#[derive(Clone)]
 pub struct Lexer<'a> {
     path: &'a str,
     head: usize,
-    escape_token: Option<char>,
+    last: Option<Token<'a>>,
+    comments: bool,
+    case_sensitive: bool,
 }
 
 impl<'a> From<&'a str> for Lexer<'a> {
@@ -32,12 +159,33 @@ impl<'a> From<&'a str> for Lexer<'a> {
         Lexer {
             path,
             head: 0,
-            escape_token: None,
+            last: None,
+            comments: false,
+            case_sensitive: false,
         }
     }
 }
 
 impl<'a> Lexer<'a> {
+    // keep_comments makes the lexer return comments as tokens rather than
+    // skipping them like whitespace, for tools like formatters
+    pub fn keep_comments(mut self, keep: bool) -> Self {
+        self.comments = keep;
+        self
+    }
+
+    // case_sensitive makes the lexer only read keywords written in upper
+    // case, any other case is read as an identifier
+    pub fn case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    // source returns the entire content being tokenized
+    pub fn source(&self) -> &'a str {
+        self.path
+    }
+
     // consumed returns the content which has already been tokenized
     // this is useful for creating error messages that point to where we
     // last were. This will not show the any peak tokens.
@@ -51,220 +199,304 @@ impl<'a> Lexer<'a> {
         &self.path[self.head..]
     }
 
-    // advance_head moves the lexor head forward by the delta
-    pub fn advance_head(&mut self, delta: usize) {
-        self.head += delta
+    // adjacent returns true when the next token directly follows the
+    // consumed content, without any whitespace or comments in between. This
+    // must be checked before peaking, since peaking consumes whitespace.
+    pub fn adjacent(&self) -> bool {
+        self.future()
+            .chars()
+            .next()
+            .is_some_and(|c| !c.is_whitespace())
+            && self.comment(self.head).is_none()
     }
 
-    // token returns the next token in the path. When there are no more tokens
-    // it returns None. All tokens returns are references to the original string
-    // meaning all escape characters are still present.
-    pub fn token(&mut self) -> Option<&'a str> {
-        let (tok, next_index) = self.full_next()?;
-        self.head = next_index;
-        Some(tok)
+    // tokens returns an iterator over every token that has not been consumed
+    pub fn tokens(mut self) -> impl Iterator<Item = Token<'a>> {
+        std::iter::from_fn(move || self.token())
     }
 
-    // peak returns the next token without moving the head forward
-    pub fn peak(&mut self) -> Option<&'a str> {
-        // keep track of "escape_token" state before the call so we can
-        // set it back to the original state after the call
-        let escaped = self.escape_token;
-        let (tok, _) = self.full_next()?;
-        self.escape_token = escaped;
+    // token returns the next token in the path. When there are no more tokens
+    // it returns None.
+    pub fn token(&mut self) -> Option<Token<'a>> {
+        let tok = self.peak()?;
+        self.head = tok.end;
+        self.last = Some(tok);
         Some(tok)
     }
 
-    // full_nest returns the next token, and stiches together tokens like >=
-    //
-    fn full_next(&mut self) -> Option<(&'a str, usize)> {
-        if self.head >= self.path.len() {
-            return None;
+    // rewind moves the head back to the start of the last token returned by
+    // token, so it is returned again
+    pub fn rewind(&mut self) {
+        if let Some(tok) = self.last {
+            self.head = tok.start;
         }
+    }
+
+    // last returns the byte range of the last token returned by token
+    pub fn last(&self) -> Range<usize> {
+        self.last.map(|tok| tok.range()).unwrap_or_default()
+    }
 
+    // last_token returns the last token returned by token
+    pub fn last_token(&self) -> Option<Token<'a>> {
+        self.last
+    }
+
+    // peak returns the next token without moving the head forward
+    pub fn peak(&mut self) -> Option<Token<'a>> {
         self.consume_whitespace();
+        self.scan(self.head)
+    }
 
-        let (tok, next_index) = self.next(self.head)?;
-        match tok {
-            // by doing a little work in special cases we can make sure
-            // we return whole tokens.
-            "=" | ">" | "<" | "!" => {
-                if let Some((tok, next_index)) = self.next(next_index) {
-                    if tok == "=" {
-                        return Some((&self.path[self.head..next_index], next_index));
-                    }
+    // advance_head moves the lexor head forward by the delta, nothing in the
+    // crate uses it any more as the head is only moved by whole tokens
+    #[deprecated(note = "use token or rewind to move the head")]
+    pub fn advance_head(&mut self, delta: usize) {
+        self.head += delta
+    }
+
+    // consume_whitespace moves the head past any whitespace, along with any
+    // comments unless they are being kept
+    fn consume_whitespace(&mut self) {
+        loop {
+            let c = self.path[self.head..].chars();
+
+            for char in c {
+                if !char.is_whitespace() {
+                    break;
                 }
+
+                self.head += char.len_utf8();
+            }
+
+            match self.comment(self.head) {
+                Some((end, _)) if !self.comments => self.head = end,
+                _ => break,
             }
-            // default case just let it fall through
-            _ => {}
+        }
+    }
+
+    // comment returns the end of the comment starting at head, and whether
+    // it was closed, when there is one. Line comments run to the end of the
+    // line, block comments run to the closing */ or the end of the query.
+    fn comment(&self, head: usize) -> Option<(usize, bool)> {
+        let rest = &self.path[head..];
+        if rest.starts_with(TOKEN_LINE_COMMENT) {
+            let end = rest.find('\n').unwrap_or(rest.len());
+            return Some((head + end, true));
         }
 
-        Some((tok, next_index))
+        if rest.starts_with(TOKEN_BLOCK_COMMENT) {
+            let body = TOKEN_BLOCK_COMMENT.len();
+            return match rest[body..].find(TOKEN_BLOCK_COMMENT_END) {
+                Some(end) => Some((head + body + end + TOKEN_BLOCK_COMMENT_END.len(), true)),
+                None => Some((self.path.len(), false)),
+            };
+        }
+
+        None
     }
 
-    // whitespace_offset will return the number of bytes from
-    // the header that are whitespace.
-    fn consume_whitespace(&mut self) {
-        // if there is an escape token we are pasing some kind of string,
-        // so we don't want to consume this or we will lose strings with whitespace
-        // at the start like ' hello' or ' '
-        if self.escape_token.is_some() {
-            return;
+    // scan returns the token starting at head. This function assumes there
+    // is no whitespace at head.
+    fn scan(&self, head: usize) -> Option<Token<'a>> {
+        let first = self.path[head..].chars().next()?;
+        let mut end = head + first.len_utf8();
+
+        if let Some((end, closed)) = self.comment(head) {
+            return Some(Token {
+                kind: TokenKind::Comment,
+                text: &self.path[head..end],
+                wrap: None,
+                closed,
+                start: head,
+                end,
+            });
         }
 
-        let c = self.path[self.head..].chars();
+        let kind = if QUOTES.contains(&first) {
+            return Some(self.scan_wrapped(head, first));
+        } else if OPERATORS.contains(&first) {
+            // stitch together comparisons like >= and ==
+            if matches!(
+                first,
+                TOKEN_EQUAL | TOKEN_NEGATE | TOKEN_GREATER | TOKEN_LESS
+            ) && self.path[end..].starts_with(TOKEN_EQUAL)
+            {
+                end += TOKEN_EQUAL.len_utf8();
+            }
+            TokenKind::Operator
+        } else if PUNCTUATION.contains(&first) {
+            TokenKind::Punctuation
+        } else {
+            end = self.scan_word(head);
+            let word = &self.path[head..end];
+            if first.is_ascii_digit() {
+                TokenKind::Number
+            } else if first == TOKEN_PARAMETER {
+                TokenKind::Parameter
+            } else if is_keyword(word, self.case_sensitive) {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Identifier
+            }
+        };
+
+        Some(Token {
+            kind,
+            text: &self.path[head..end],
+            wrap: None,
+            closed: true,
+            start: head,
+            end,
+        })
+    }
+
+    // scan_word returns the end of the word starting at head. Words run
+    // until whitespace or a special character, special characters can be
+    // included in a word by escaping them with \
+    fn scan_word(&self, head: usize) -> usize {
+        let mut end = head;
+        let mut escape_next = false;
 
-        for char in c {
-            if !char.is_whitespace() {
+        for char in self.path[head..].chars() {
+            if escape_next {
+                escape_next = false;
+            } else if char == TOKEN_ESCAPE {
+                escape_next = true;
+            } else if char.is_whitespace()
+                || OPERATORS.contains(&char)
+                || PUNCTUATION.contains(&char)
+                || QUOTES.contains(&char)
+            {
                 break;
             }
 
-            self.head += char.len_utf8();
+            end += char.len_utf8();
         }
+
+        end
     }
 
-    // next returns the next token without moving the head forward. Tokens viewed
-    // this way will not show up in consumed until token is called. This internal
-    // function does not stitch tokens together like >=. Use peak instead. This function
-    // also assumes there is no whitespace at the head specified
-    fn next(&mut self, head: usize) -> Option<(&'a str, usize)> {
-        let c = self.path[head..].chars();
-        let mut tok: Option<&str> = None;
-        let mut next_index = head;
-        let mut escape_next = false;
+    // scan_wrapped returns the token wrapped in quote starting at head,
+    // everything up to the closing quote is part of the token
+    fn scan_wrapped(&self, head: usize, quote: char) -> Token<'a> {
+        let body = head + quote.len_utf8();
+        let kind = match quote {
+            TOKEN_STRING_LITERAL => TokenKind::String,
+            _ => TokenKind::Identifier,
+        };
 
-        let escape_all = match self.escape_token {
-            None => false,
-            Some(_) => true,
+        let mut tok = Token {
+            kind,
+            text: &self.path[body..],
+            wrap: Some(&self.path[head..body]),
+            closed: false,
+            start: head,
+            end: self.path.len(),
         };
 
-        // important, for every path out of this loop you MUST consider what to
-        // do with next_index given your context.
-        'charloop: for char in c {
-            if !escape_all && char.is_whitespace() {
-                break 'charloop;
+        let mut escape_next = false;
+        for (i, char) in self.path[body..].char_indices() {
+            if escape_next {
+                escape_next = false;
+            } else if char == TOKEN_ESCAPE {
+                escape_next = true;
+            } else if char == quote {
+                tok.text = &self.path[body..body + i];
+                tok.closed = true;
+                tok.end = body + i + quote.len_utf8();
+                break;
             }
+        }
 
-            // Any special characters should be listed here. They can all be escaped
-            match char {
-                TOKEN_EQUAL
-                | TOKEN_LESS
-                | TOKEN_NEGATE
-                | TOKEN_GREATER
-                | TOKEN_PARENTHESIS_OPEN
-                | TOKEN_PARENTHESIS_CLOSE
-                | TOKEN_CURLEY_OPEN
-                | TOKEN_CURLEY_CLOSE
-                | TOKEN_COMMA
-                | TOKEN_COLON
-                | TOKEN_SQUARE_OPEN
-                | TOKEN_SQUARE_CLOSE
-                | TOKEN_PLUS
-                | TOKEN_MINUS
-                | TOKEN_DIVIDE
-                | TOKEN_MULTIPLY
-                | TOKEN_MODULUS
-                | TOKEN_EXPONENT
-                | TOKEN_TICK => {
-                    // if the previous token was an escape token, we just want to add this to the
-                    // existing token
-                    if escape_next || escape_all {
-                        next_index += char.len_utf8();
-                        tok = Some(&self.path[head..next_index]);
-                        escape_next = false;
-                        continue 'charloop;
-                    }
-
-                    // check the token, if there is a value in there we consumed
-                    // an identifier first, so we need to return that. We keep
-                    // keep track of the previous_index so that tokens greater
-                    // than a single byte can be used.
-                    if let Some(_) = tok {
-                        break 'charloop;
-                    }
-
-                    // otherwise we are starting with a token, so lets return that
-                    next_index += char.len_utf8();
-                    tok = Some(&self.path[head..next_index]);
-                    break 'charloop;
-                }
-                // Drives escaping logic, this will escape the next character
-                TOKEN_ESCAPE => {
-                    // collect the escapes so we can return something if the input is "\\\\\\\"
-                    tok = Some(&self.path[head..next_index]);
-                    next_index += char.len_utf8();
-                    escape_next = true;
-                }
-                // If something wraps something, and you want to escape everything inside
-                // you should put it here. The wrapping token will be returned as it's
-                // own token, and then the contents will be returned as a single token
-                // followed by another wrapping token. Can use \ to escape. Escaped strings
-                // are returned as is (with their escaping characters) so we can return a
-                // reference
-                TOKEN_KEY | TOKEN_STRING_LITERAL => {
-                    // if the previous character was an escape we can ignore
-                    // the double quote
-
-                    if escape_next {
-                        escape_next = false;
-                        next_index += char.len_utf8();
-                        tok = Some(&self.path[head..next_index]);
-                        continue;
-                    }
-
-                    // make sure the ending token matches the starting token
-                    // we may add more string wrappers in the future, besides
-                    // these two.
-                    if let Some(t) = self.escape_token {
-                        if t != char {
-                            next_index += char.len_utf8();
-                            tok = Some(&self.path[head..next_index]);
-                            continue;
-                        }
-                    }
-
-                    // return the wrapping token as it's own token.
-                    if let None = tok {
-                        next_index += char.len_utf8();
-                        tok = Some(&self.path[head..next_index]);
-
-                        // toggle escaping when we encounter an escape token
-                        match self.escape_token {
-                            None => self.escape_token = Some(char),
-                            Some(_) => self.escape_token = None,
-                        }
-
-                        break 'charloop;
-                    }
-
-                    if let Some(_) = self.escape_token {
-                        // after we turn it off collect the token, without
-                        // grabbing the ending "
-                        tok = Some(&self.path[head..next_index]);
-                        break 'charloop;
-                    }
-
-                    // if we are just starting we want to collect the previous
-                    // token if it exists
-                    if let Some(_) = tok {
-                        break 'charloop;
-                    }
-                }
-                // Anything not special should be wrapped up here
-                _ => {
-                    escape_next = false;
-                    next_index += char.len_utf8();
-                    tok = Some(&self.path[head..next_index]);
+        tok
+    }
+}
+
+// is_keyword returns true when word is one of the KEYWORDS
+fn is_keyword(word: &str, case_sensitive: bool) -> bool {
+    KEYWORDS.iter().any(|k| {
+        if case_sensitive {
+            *k == word
+        } else {
+            k.eq_ignore_ascii_case(word)
+        }
+    })
+}
+
+// unescape decodes the escape sequences in the text of a token. The
+// accepted escapes are \n, \t, \r, \u{1F600} and the escaped quotes \',
+// \" and \` and escape character \\. Any other escape is an error.
+pub(crate) fn unescape(text: &str) -> Result<String> {
+    let mut value = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            TOKEN_ESCAPE => value.push(unescape_next(&mut chars)?),
+            char => value.push(char),
+        }
+    }
+
+    Ok(value)
+}
+
+// unescape_next decodes a single escape sequence from chars, the escape
+// character itself must already have been read. Only the escapes written
+// by escape are accepted, any other character is an invalid escape.
+pub(crate) fn unescape_next(chars: &mut impl Iterator<Item = char>) -> Result<char> {
+    match chars.next() {
+        Some('n') => Ok('\n'),
+        Some('t') => Ok('\t'),
+        Some('r') => Ok('\r'),
+        Some('u') => {
+            let invalid = |code: &str| Error::InvalidQuery(format!("invalid escape \\u{}", code));
+            if chars.next() != Some('{') {
+                return Err(invalid(""));
+            }
+
+            let mut code = String::new();
+            loop {
+                match chars.next() {
+                    Some(TOKEN_CURLEY_CLOSE) => break,
+                    Some(c) => code.push(c),
+                    None => return Err(invalid(&format!("{{{}", code))),
                 }
             }
+
+            u32::from_str_radix(&code, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| invalid(&format!("{{{}}}", code)))
         }
+        Some(char @ (TOKEN_STRING_LITERAL | TOKEN_KEY | TOKEN_TICK | TOKEN_ESCAPE)) => Ok(char),
+        Some(char) => Err(Error::InvalidQuery(format!("invalid escape \\{}", char))),
+        None => Err(Error::InvalidQuery(String::from(
+            "escape character at the end of the text",
+        ))),
+    }
+}
 
-        if let Some(tok) = tok {
-            Some((tok, next_index))
-        } else {
-            None
+// escape is the reverse of unescape. The escape character, control
+// characters and any of the quotes are escaped so value can be written
+// wrapped in one of the quotes and read back.
+pub(crate) fn escape(value: &str, quotes: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    for char in value.chars() {
+        match char {
+            '\n' => text.push_str("\\n"),
+            '\t' => text.push_str("\\t"),
+            '\r' => text.push_str("\\r"),
+            c if c.is_control() => text.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c if c == TOKEN_ESCAPE || quotes.contains(c) => {
+                text.push(TOKEN_ESCAPE);
+                text.push(c);
+            }
+            c => text.push(c),
         }
     }
+
+    text
 }
 
 #[cfg(test)]
@@ -299,7 +531,7 @@ mod test {
 
             let mut tok = p.token();
             while let Some(t) = tok {
-                toks.push(t);
+                toks.push(t.to_string());
                 tok = p.token();
             }
 
@@ -316,39 +548,25 @@ mod test {
             "from",
             "something",
             "where",
-            "'",
-            "name",
-            "'",
+            "'name'",
             "=",
-            "\"",
-            "happy",
-            "\""
+            "\"happy\""
         );
 
         test_lexor!(
             "SELECT '~.\"key name\"' FROM 'namespace' WHERE '*[0].name'==5 AND 'chiken'!='egg'",
             "SELECT",
-            "'",
-            "~.\"key name\"",
-            "'",
+            "'~.\"key name\"'",
             "FROM",
-            "'",
-            "namespace",
-            "'",
+            "'namespace'",
             "WHERE",
-            "'",
-            "*[0].name",
-            "'",
+            "'*[0].name'",
             "==",
             "5",
             "AND",
-            "'",
-            "chiken",
-            "'",
+            "'chiken'",
             "!=",
-            "'",
-            "egg",
-            "'"
+            "'egg'"
         );
 
         test_lexor!(
@@ -363,17 +581,127 @@ mod test {
         );
 
         test_lexor!("()", "(", ")");
-        test_lexor!(r#" "a" == "b" "#, "\"", "a", "\"", "==", "\"", "b", "\"");
+        test_lexor!(
+            "`a.\"key name\"[0]` + b.c[1]",
+            "`a.\"key name\"[0]`",
+            "+",
+            "b.c",
+            "[",
+            "1",
+            "]"
+        );
+        test_lexor!(r#" "a" == "b" "#, "\"a\"", "==", "\"b\"");
 
         test_lexor!(
             r#"length("\"a.b.c\"")"#,
             "length",
             "(",
-            "\"",
-            r#"\"a.b.c\""#,
-            "\"",
+            r#""\"a.b.c\"""#,
             ")"
         );
+
+        test_lexor!("' padded '", "' padded '");
+        test_lexor!("'unclosed", "'unclosed");
+    }
+
+    #[test]
+    fn test_token() {
+        let source = "SELECT to_upper(`from`) AS \"x\"\nFROM events WHERE a >= 1.5 AND b = $1";
+        let toks: Vec<_> = Lexer::from(source)
+            .tokens()
+            .map(|t| (t.kind(), t.text()))
+            .collect();
+
+        assert_eq!(
+            toks,
+            vec![
+                (TokenKind::Keyword, "SELECT"),
+                (TokenKind::Identifier, "to_upper"),
+                (TokenKind::Punctuation, "("),
+                (TokenKind::Identifier, "from"),
+                (TokenKind::Punctuation, ")"),
+                (TokenKind::Keyword, "AS"),
+                (TokenKind::Identifier, "x"),
+                (TokenKind::Keyword, "FROM"),
+                (TokenKind::Identifier, "events"),
+                (TokenKind::Keyword, "WHERE"),
+                (TokenKind::Identifier, "a"),
+                (TokenKind::Operator, ">="),
+                (TokenKind::Number, "1.5"),
+                (TokenKind::Keyword, "AND"),
+                (TokenKind::Identifier, "b"),
+                (TokenKind::Operator, "="),
+                (TokenKind::Parameter, "$1"),
+            ]
+        );
+
+        let tok = Lexer::from(source).tokens().nth(3).unwrap();
+        assert_eq!(tok.range(), 16..22);
+        assert_eq!(tok.wrap(), Some("`"));
+        assert!(!tok.is("FROM"));
+
+        let tok = Lexer::from(source).tokens().nth(7).unwrap();
+        assert!(tok.is("from"));
+        assert_eq!(tok.span(source).to_string(), "2:1");
+
+        let tok = Lexer::from("'open").tokens().next().unwrap();
+        assert_eq!(
+            (tok.kind(), tok.text(), tok.closed()),
+            (TokenKind::String, "open", false)
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        test_lexor!(
+            "SELECT a -- the a\n, 'b -- c' /* block\n comment */ FROM -- end",
+            "SELECT",
+            "a",
+            ",",
+            "'b -- c'",
+            "FROM"
+        );
+        test_lexor!("a /* open", "a");
+        test_lexor!("a/**/b-c", "a", "b", "-", "c");
+
+        let toks: Vec<_> = Lexer::from("a -- one\n/* two */ b /* three")
+            .keep_comments(true)
+            .tokens()
+            .map(|t| (t.kind(), t.text(), t.closed()))
+            .collect();
+        assert_eq!(
+            toks,
+            vec![
+                (TokenKind::Identifier, "a", true),
+                (TokenKind::Comment, "-- one", true),
+                (TokenKind::Comment, "/* two */", true),
+                (TokenKind::Identifier, "b", true),
+                (TokenKind::Comment, "/* three", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_escape() -> Result<()> {
+        assert_eq!(unescape(r"it\'s")?, "it's");
+        assert_eq!(unescape(r"a\\b\nc\td")?, "a\\b\nc\td");
+        assert_eq!(unescape(r"\u{1F600} \u{e9}")?, "\u{1F600} é");
+        assert_eq!(unescape(r#"\"\`\r"#)?, "\"`\r");
+        assert!(unescape(r"\.").is_err());
+        assert!(unescape(r"\x41").is_err());
+        assert!(unescape(r"\u{41").is_err());
+        assert!(unescape(r"\u{41 and more text").is_err());
+        assert!(unescape(r"\u{zz}").is_err());
+        assert!(unescape(r"\u00e9").is_err());
+        assert!(unescape(r"\u{110000}").is_err());
+        assert!(unescape("end\\").is_err());
+
+        let value = "it's a \"test\"\n\\\t\u{7}";
+        assert_eq!(escape(value, "'"), r#"it\'s a "test"\n\\\t\u{7}"#);
+        assert_eq!(unescape(&escape(value, "'"))?, value);
+        assert_eq!(escape(value, "\"`"), r#"it's a \"test\"\n\\\t\u{7}"#);
+
+        Ok(())
     }
 
     #[test]
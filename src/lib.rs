@@ -1,13 +1,33 @@
+mod builder;
+mod condition;
 mod container;
 mod error;
+mod executor;
 mod expression;
+mod format;
 mod lexor;
-mod parser;
+mod params;
+pub mod parser;
+mod query;
 mod serde;
+mod span;
+mod stream;
 mod types;
+mod visitor;
 
+pub use crate::serde::AST_VERSION;
+pub use builder::*;
+pub use condition::*;
 pub use container::*;
 pub use error::*;
+pub use executor::*;
 pub use expression::*;
-pub use parser::Parser;
+pub use format::*;
+pub use lexor::{Lexer, Token, TokenKind};
+pub use params::*;
+pub use parser::{Parser, ParserBuilder};
+pub use query::*;
+pub use span::*;
+pub use stream::*;
 pub use types::*;
+pub use visitor::*;
@@ -0,0 +1,130 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{Any, Container, Kind, Parameter, Str};
+
+// Params holds the values bound to the parameters of a query. Values are
+// never written into the query, so one parsed query can be run with
+// different values without splicing user input into the query:
+//
+// let query = Parser::from("SELECT * FROM events WHERE host = :host").query()?;
+// let params = Params::new().bind("host", "a");
+// let rows = query.execute_with(&params, &events)?;
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Params {
+    values: HashMap<Parameter, Any<'static>>,
+}
+
+impl Params {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // bind binds a value to a parameter, replacing any value already bound.
+    // Numbers bind positional parameters like `$1`, strings bind named
+    // parameters like `:name`.
+    pub fn bind<'v>(mut self, param: impl Into<Parameter>, value: impl Into<Any<'v>>) -> Self {
+        self.values.insert(param.into(), value.into().into_owned());
+        self
+    }
+
+    // get returns the value bound to the parameter
+    pub fn get(&self, param: &Parameter) -> Option<&Any<'static>> {
+        self.values.get(param)
+    }
+
+    // container binds the params to a container, parameters evaluated
+    // against the returned container read their values from the params
+    pub fn container<T: Container>(&self, c: T) -> Bound<'_, T> {
+        Bound::new(c, Some(self))
+    }
+}
+
+// Bound is a container with params bound to it, everything other than
+// parameters is read from the container it wraps.
+#[derive(Debug)]
+pub struct Bound<'p, T: Container> {
+    container: T,
+    params: Option<&'p Params>,
+}
+
+impl<'p, T: Container> Bound<'p, T> {
+    pub(crate) fn new(container: T, params: Option<&'p Params>) -> Self {
+        Bound { container, params }
+    }
+}
+
+impl<T: Container> Display for Bound<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.container)
+    }
+}
+
+impl<T: Container> Container for Bound<'_, T> {
+    fn value(&self) -> Any<'_> {
+        self.container.value()
+    }
+
+    fn kind(&self) -> Kind {
+        self.container.kind()
+    }
+
+    fn len(&self) -> usize {
+        self.container.len()
+    }
+
+    fn keys(&self) -> Box<dyn Iterator<Item = Str<'_>> + '_> {
+        self.container.keys()
+    }
+
+    fn get(&self, key: &str) -> Option<Any<'_>> {
+        self.container.get(key)
+    }
+
+    fn index(&self, index: usize) -> Option<Any<'_>> {
+        self.container.index(index)
+    }
+
+    fn children(&self) -> Box<dyn Iterator<Item = Any<'_>> + '_> {
+        self.container.children()
+    }
+
+    fn aggregate(&self, name: &str) -> Option<Any<'_>> {
+        self.container.aggregate(name)
+    }
+
+    fn parameter(&self, param: &Parameter) -> Option<Any<'_>> {
+        match self.params {
+            Some(params) => params.get(param).map(Any::reborrow),
+            None => self.container.parameter(param),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Error, Parser, Result};
+
+    #[test]
+    fn test_params() -> Result<()> {
+        let doc: Any = serde_json::from_str(r#"{"a": 5}"#).unwrap();
+        let params = Params::new().bind(1, 2).bind("name", "x").bind(1, 3);
+
+        let expr = Parser::from("a * $1").expression()?;
+        assert_eq!(expr.evaluate(&params.container(&doc))?, Any::from(15));
+
+        let expr = Parser::from(":name").expression()?;
+        assert_eq!(expr.evaluate(&params.container(&doc))?, Any::from("x"));
+
+        let expr = Parser::from("$2").expression()?;
+        assert_eq!(
+            expr.evaluate(&params.container(&doc)).unwrap_err(),
+            Error::UnboundParameter {
+                parameter: Parameter::Positional(2)
+            }
+        );
+        assert!(expr.evaluate(&doc).is_err());
+
+        Ok(())
+    }
+}
@@ -1,6 +1,15 @@
-use std::{cell::RefCell, collections::HashMap, time::Duration};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    ops::Range,
+    time::Duration,
+};
 
-use super::{Error, History, Result, expression::*, lexor::Lexer};
+use super::{
+    Error, History, Result, Span,
+    expression::*,
+    lexor::{Lexer, Token, TokenKind, unescape},
+};
 
 pub const SELECT: &str = "SELECT";
 pub const SELECT_SEP: &str = ",";
@@ -14,7 +23,11 @@ pub const ORDER: &str = "ORDER";
 pub const BY: &str = "BY";
 pub const ORDER_ASC: &str = "ASC";
 pub const ORDER_DESC: &str = "DESC";
+pub const ORDER_NULLS: &str = "NULLS";
+pub const ORDER_NULLS_FIRST: &str = "FIRST";
+pub const ORDER_NULLS_LAST: &str = "LAST";
 pub const LIMIT: &str = "LIMIT";
+pub const OFFSET: &str = "OFFSET";
 pub const INTERVAL: &str = "INTERVAL";
 pub const EVICT: &str = "EVICT";
 pub const EMIT: &str = "EMIT";
@@ -32,6 +45,7 @@ pub const LESS_THAN_EQUAL: &str = "<=";
 pub const AND: &str = "AND";
 pub const OR: &str = "OR";
 pub const NEGATE: &str = "!";
+pub const NOT: &str = "NOT";
 pub const KEY_WRAP: &str = "`";
 pub const IDENTIFIER_WRAP: &str = "\"";
 pub const STRING_WRAP: &str = "'";
@@ -55,20 +69,204 @@ pub const EXPONENT: &str = "^";
 pub const SUB_EXPR_OPEN: &str = "(";
 pub const SUB_EXPR_CLOSE: &str = ")";
 
+pub const PARAM_POSITIONAL: &str = "$";
+pub const PARAM_NAMED: &str = ":";
+
 pub const FN_OPEN: &str = "(";
 pub const FN_CLOSE: &str = ")";
 pub const FN_SEP: &str = ",";
 
 pub const FN_EXISTS: &str = "EXISTS";
+pub const FN_TO_UPPER: &str = "TO_UPPER";
 
 pub const AGGREGATION_SUM: &str = "SUM";
 pub const AGGREGATION_COUNT: &str = "COUNT";
 pub const AGGREGATION_AVG: &str = "AVG";
 
+// KEYWORDS are reserved, they are never read as paths or namespaces unless
+// they are wrapped like `from`
+pub const KEYWORDS: [&str; 26] = [
+    SELECT,
+    SELECT_ALIAS,
+    FROM,
+    WHERE,
+    HAVING,
+    GROUP,
+    ORDER,
+    BY,
+    ORDER_ASC,
+    ORDER_DESC,
+    ORDER_NULLS,
+    ORDER_NULLS_FIRST,
+    ORDER_NULLS_LAST,
+    LIMIT,
+    OFFSET,
+    INTERVAL,
+    EVICT,
+    EMIT,
+    ON,
+    IN,
+    AND,
+    OR,
+    NOT,
+    NULL,
+    TRUE,
+    FALSE,
+];
+
+// CLAUSES are the keywords which start a clause. After an error a recovering
+// parser skips ahead to one of these so it can carry on parsing.
+pub const CLAUSES: [&str; 11] = [
+    SELECT, FROM, WHERE, GROUP, HAVING, INTERVAL, EVICT, EMIT, ORDER, LIMIT, OFFSET,
+];
+
 // Parser is used to parse a query string into a query struct, it produces all
 // sorts of interior structs as well.
 pub struct Parser<'a> {
     lex: RefCell<Lexer<'a>>,
+    // diagnostics holds the errors found so far while recovering, it is
+    // None when the parser stops at the first error
+    diagnostics: RefCell<Option<Vec<Error>>>,
+    options: ParserBuilder,
+    // depth is how many expressions and conditions are currently being
+    // parsed inside each other
+    depth: Cell<usize>,
+    // height is the most operators stacked on top of each other by the
+    // chains parsed so far within the current operand, see chain
+    height: Cell<usize>,
+    // tokens is how many tokens have been read, counting tokens read again
+    // after restoring a checkpoint
+    tokens: Cell<usize>,
+}
+
+const DEFAULT_MAX_DEPTH: usize = 64;
+const DEFAULT_MAX_CHAIN: usize = 1024;
+
+// ParserBuilder configures the dialect of DQL a parser accepts, so products
+// embedding DQL can be stricter than the default dialect. It also sets the
+// limits on the size of a query, which keep parsing untrusted queries from
+// exhausting the stack or memory. Only the depth and the length of chains
+// are limited by default.
+//
+// let parser = ParserBuilder::new()
+//     .case_sensitive(true)
+//     .functions([AGGREGATION_COUNT])
+//     .build("SELECT COUNT(*) FROM events");
+#[derive(Debug, Clone)]
+pub struct ParserBuilder {
+    case_sensitive: bool,
+    bare_paths: bool,
+    functions: Option<HashSet<String>>,
+    max_depth: usize,
+    max_chain: usize,
+    max_tokens: usize,
+    max_literal_size: usize,
+    max_arguments: usize,
+    double_equal: bool,
+}
+
+impl Default for ParserBuilder {
+    fn default() -> Self {
+        ParserBuilder {
+            case_sensitive: false,
+            bare_paths: true,
+            functions: None,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_chain: DEFAULT_MAX_CHAIN,
+            max_tokens: usize::MAX,
+            max_literal_size: usize::MAX,
+            max_arguments: usize::MAX,
+            double_equal: true,
+        }
+    }
+}
+
+impl ParserBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // case_sensitive makes keywords only count when they are written in
+    // upper case, like `SELECT`. Keywords in any other case are read as
+    // paths. Function names are never case sensitive.
+    pub fn case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    // bare_paths sets whether paths can be written without being wrapped in
+    // KEY_WRAP, like `a.b`. When false bare paths are an error.
+    pub fn bare_paths(mut self, bare_paths: bool) -> Self {
+        self.bare_paths = bare_paths;
+        self
+    }
+
+    // functions limits the functions and aggregations a query can call to
+    // the names given, calling any other function is an error. Every
+    // function is available by default.
+    pub fn functions<S: AsRef<str>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        let names = names.into_iter().map(|n| n.as_ref().to_uppercase());
+        self.functions = Some(names.collect());
+        self
+    }
+
+    // max_depth sets how deeply expressions and conditions can be nested
+    // inside each other, like `((a))` or `[[1]]`. Parsing recurses for each
+    // level, so a very large depth can overflow the stack.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    // max_chain sets how many operators a chain like `a + b + c` or
+    // `a AND b` can hold, counting the operators of any chain it is nested
+    // in. Chains are parsed into a tree which is one level deeper for each
+    // operator, and evaluating it recurses for each level, so a very long
+    // chain can overflow the stack.
+    pub fn max_chain(mut self, max_chain: usize) -> Self {
+        self.max_chain = max_chain;
+        self
+    }
+
+    // max_tokens sets how many tokens can be read while parsing. Tokens read
+    // again when the parser backtracks count again, so this also limits the
+    // work done by the parser.
+    pub fn max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    // max_literal_size sets the largest string literal in bytes, and the
+    // most items a list or map literal can hold
+    pub fn max_literal_size(mut self, max_literal_size: usize) -> Self {
+        self.max_literal_size = max_literal_size;
+        self
+    }
+
+    // max_arguments sets how many arguments a function can be called with
+    pub fn max_arguments(mut self, max_arguments: usize) -> Self {
+        self.max_arguments = max_arguments;
+        self
+    }
+
+    // double_equal sets whether `==` is accepted as well as `=`
+    pub fn double_equal(mut self, double_equal: bool) -> Self {
+        self.double_equal = double_equal;
+        self
+    }
+
+    // build returns a parser for source using this dialect
+    pub fn build<'a>(&self, source: &'a str) -> Parser<'a> {
+        let lex = Lexer::from(source).case_sensitive(self.case_sensitive);
+        Parser {
+            lex: RefCell::new(lex),
+            diagnostics: RefCell::new(None),
+            options: self.clone(),
+            depth: Cell::new(0),
+            height: Cell::new(0),
+            tokens: Cell::new(0),
+        }
+    }
 }
 
 // must_token consumes and returns the next token, if we have run out
@@ -76,9 +274,7 @@ pub struct Parser<'a> {
 // case sensitive
 macro_rules! must_token {
     ( $source:ident ) => {
-        $source
-            .token()
-            .ok_or_else(|| crate::Error::unexpected_eof($source.history()))
+        $source.must_token()
     };
 }
 pub(crate) use must_token;
@@ -95,10 +291,7 @@ pub(crate) use consume;
 // token without consuming it. This function is not case sensative
 macro_rules! is_next {
     ( $source:ident, $seen:expr ) => {
-        peak!($source)
-            .map(|v| v.to_uppercase())
-            .filter(|v| v == $seen)
-            .is_some()
+        $source.peak().filter(|v| v.is($seen)).is_some()
     };
 }
 pub(crate) use is_next;
@@ -112,9 +305,8 @@ macro_rules! continue_if {
     ( $source:ident, $seen:expr ) => {
         $source
             .peak()
-            .map(|v| v.to_uppercase())
-            .filter(|v| v == $seen)
-            .inspect(|_| consume!($source))
+            .filter(|v| v.is($seen))
+            .inspect(|_| crate::parser::consume!($source))
             .is_some()
     };
 }
@@ -126,7 +318,7 @@ pub(crate) use continue_if;
 macro_rules! consume_next {
     ( $source:ident, $expected:expr ) => {
         crate::parser::must_token!($source).and_then(|v| {
-            if v.to_uppercase() == $expected {
+            if v.is($expected) {
                 Ok(())
             } else {
                 Err(crate::Error::with_history(
@@ -139,188 +331,565 @@ macro_rules! consume_next {
 }
 pub(crate) use consume_next;
 
-// TODO: this is stupid, and we need to change this to a parser
-// builder
+// is_boundary returns true for tokens a recovering parser can carry on from
+fn is_boundary(tok: &Token<'_>, separator: bool) -> bool {
+    (separator && tok.is(SELECT_SEP))
+        || (tok.kind() == TokenKind::Keyword && CLAUSES.iter().any(|v| tok.is(v)))
+}
+
+// From parses source with the default dialect, see ParserBuilder to
+// configure the dialect
 impl<'a> From<&'a str> for Parser<'a> {
     fn from(s: &'a str) -> Parser<'a> {
-        Parser {
-            lex: RefCell::new(Lexer::from(s)),
-        }
+        ParserBuilder::default().build(s)
     }
 }
 
 impl<'a> Parser<'a> {
     // peak is a shortcut for self.lex.peak and it returns the next
     // token from the tokenizer without consuming it
-    pub fn peak(&self) -> Option<&str> {
+    pub fn peak(&self) -> Option<Token<'a>> {
         self.lex.borrow_mut().peak()
     }
 
     // token returns the next token from the tokenizer, it *does* consume
     // the token, moving the head forward.
-    pub fn token(&self) -> Option<&str> {
-        self.lex.borrow_mut().token()
+    pub fn token(&self) -> Option<Token<'a>> {
+        let tok = self.lex.borrow_mut().token()?;
+        self.tokens.set(self.tokens.get() + 1);
+        Some(tok)
     }
-    // consumed returns a History object, which lets the caller know where
-    // the head of the lexor is. This is useful for creating error messages
-    // since you can point out where problems are
-    pub fn history(&self) -> History {
-        History::new(self.lex.borrow().consumed(), self.lex.borrow().future())
+
+    // must_token returns the next token like token, returning an error when
+    // there are no more tokens or the max tokens have already been read
+    pub(crate) fn must_token(&self) -> Result<Token<'a>> {
+        if self.tokens.get() >= self.options.max_tokens {
+            return Err(Error::limit_exceeded(
+                &format!("query has more than {} tokens", self.options.max_tokens),
+                self.history(),
+            ));
+        }
+
+        self.token()
+            .ok_or_else(|| Error::unexpected_eof(self.history()))
+    }
+    // adjacent returns true when the next token directly follows the last
+    // token consumed, this must be checked before peaking
+    pub(crate) fn adjacent(&self) -> bool {
+        self.lex.borrow().adjacent()
+    }
+
+    // checkpoint returns the current state of the lexer so the parser can
+    // try to parse something and rewind with restore if it fails.
+    pub(crate) fn checkpoint(&self) -> Lexer<'a> {
+        self.lex.borrow().clone()
     }
 
-    // parse_identifier allows you to parse a string with an optional wrapping
-    // token. This should be used for things that could be passed unwrapped or
-    // wrapped, like the `FROM` clause values.
-    pub fn parse_identifier(&mut self, wrap: &str) -> Result<String> {
-        let wrapped = continue_if!(self, wrap);
+    // restore rewinds the parser to a checkpoint
+    pub(crate) fn restore(&self, checkpoint: Lexer<'a>) {
+        *self.lex.borrow_mut() = checkpoint;
+    }
 
-        let value = must_token!(self)?;
+    // diagnose runs parse with recovery turned on, returning its result
+    // along with every error which was recovered from
+    pub(crate) fn diagnose<T>(&self, parse: impl FnOnce() -> Result<T>) -> (Result<T>, Vec<Error>) {
+        *self.diagnostics.borrow_mut() = Some(Vec::new());
+        let result = parse();
+        (result, self.diagnostics.take().unwrap_or_default())
+    }
 
-        if wrapped {
-            consume_next!(self, wrap)?;
+    // recover handles an error found while parsing. Normally the error is
+    // returned so parsing stops. While recovering the error is recorded and
+    // the parser skips ahead to the next clause keyword, or the next `,` when
+    // separator is true, ignoring anything wrapped in brackets on the way.
+    pub(crate) fn recover(&self, err: Error, separator: bool) -> Result<()> {
+        match self.diagnostics.borrow_mut().as_mut() {
+            Some(diagnostics) => diagnostics.push(err),
+            None => return Err(err),
+        }
+
+        let mut depth = 0usize;
+        while let Some(tok) = self.peak() {
+            if [SUB_EXPR_OPEN, ARRAY_WRAP, MAP_WRAP]
+                .iter()
+                .any(|v| tok.is(v))
+            {
+                depth += 1;
+            } else if [SUB_EXPR_CLOSE, ARRAY_WRAP_END, MAP_WRAP_END]
+                .iter()
+                .any(|v| tok.is(v))
+            {
+                depth = depth.saturating_sub(1);
+            } else if depth == 0 && is_boundary(&tok, separator) {
+                break;
+            }
+            consume!(self);
         }
 
-        Ok(String::from(value))
+        Ok(())
     }
 
-    pub fn parse_duration(&mut self, wrap: &str) -> Result<Duration> {
-        let wrapped = continue_if!(self, wrap);
-        if wrapped {
-            consume!(self);
+    // recoverable runs parse, passing any error it returns to recover. None is
+    // returned when parse failed and the parser recovered from it. When parse
+    // failed on a clause keyword or separator that token is put back, so
+    // parsing picks up from it rather than skipping past it.
+    pub(crate) fn recoverable<T>(
+        &self,
+        separator: bool,
+        parse: impl FnOnce() -> Result<T>,
+    ) -> Result<Option<T>> {
+        let start = self.lex.borrow().consumed().len();
+        let err = match parse() {
+            Ok(v) => return Ok(Some(v)),
+            Err(err) => err,
+        };
+
+        {
+            let mut lex = self.lex.borrow_mut();
+            if lex
+                .last_token()
+                .is_some_and(|tok| tok.range().start >= start && is_boundary(&tok, separator))
+            {
+                lex.rewind();
+            }
         }
 
-        let value = must_token!(self)?;
+        self.recover(err, separator).map(|_| None)
+    }
 
-        if wrapped {
-            consume_next!(self, wrap)?;
+    // nested runs parse one level deeper, returning an error rather than
+    // running parse when that is deeper than the max depth
+    pub(crate) fn nested<T>(&self, parse: impl FnOnce() -> Result<T>) -> Result<T> {
+        let depth = self.depth.get() + 1;
+        if depth > self.options.max_depth {
+            return Err(Error::limit_exceeded(
+                &format!(
+                    "query is nested more than {} levels deep",
+                    self.options.max_depth
+                ),
+                self.history(),
+            ));
         }
 
-        parse_duration::parse(value)
-            .map_err(|err| Error::InvalidQuery(format!("invalid duration {}", err)))
+        self.depth.set(depth);
+        let result = parse();
+        self.depth.set(depth - 1);
+        result
+    }
+
+    // chain runs parse for a left associative chain like `a + b + c`. parse
+    // is given a link function to call before each operator. The chain is
+    // parsed into a tree one level deeper for every operator, on top of the
+    // tallest chain within its operands, and an error is returned once that
+    // is higher than the max chain.
+    pub(crate) fn chain<T>(
+        &self,
+        parse: impl FnOnce(&dyn Fn() -> Result<()>) -> Result<T>,
+    ) -> Result<T> {
+        let outer = self.height.replace(0);
+        let operators = Cell::new(0);
+        let operands = Cell::new(0);
+
+        let must_height = |operators: usize| {
+            operands.set(operands.get().max(self.height.replace(0)));
+            if operators + operands.get() <= self.options.max_chain {
+                return Ok(());
+            }
+
+            Err(Error::limit_exceeded(
+                &format!(
+                    "query has a chain of more than {} operators",
+                    self.options.max_chain
+                ),
+                self.history(),
+            ))
+        };
+
+        let result = parse(&|| {
+            operators.set(operators.get() + 1);
+            must_height(operators.get())
+        })
+        .and_then(|value| must_height(operators.get()).map(|_| value));
+
+        self.height.set(outer.max(operators.get() + operands.get()));
+        result
+    }
+
+    // must_argument returns an error when a function is called with more
+    // than the max arguments, index is the argument about to be parsed
+    pub(crate) fn must_argument(&self, index: usize) -> Result<()> {
+        if index < self.options.max_arguments {
+            return Ok(());
+        }
+
+        Err(Error::limit_exceeded(
+            &format!(
+                "function has more than {} arguments",
+                self.options.max_arguments
+            ),
+            self.history(),
+        ))
+    }
+
+    // must_literal_size returns an error when a literal holds more than the
+    // max literal size, kind names the literal in the error
+    fn must_literal_size(&self, kind: &str, size: usize) -> Result<()> {
+        if size <= self.options.max_literal_size {
+            return Ok(());
+        }
+
+        Err(Error::limit_exceeded(
+            &format!(
+                "{} literal is larger than {}",
+                kind, self.options.max_literal_size
+            ),
+            self.history(),
+        ))
+    }
+
+    // has_function returns true when the dialect allows calling the function
+    pub(crate) fn has_function(&self, name: &str) -> bool {
+        self.options
+            .functions
+            .as_ref()
+            .is_none_or(|names| names.contains(&name.to_uppercase()))
+    }
+
+    // double_equal returns true when the dialect accepts `==`
+    pub(crate) fn double_equal(&self) -> bool {
+        self.options.double_equal
+    }
+
+    // consumed returns a History object, which lets the caller know where
+    // the head of the lexor is. This is useful for creating error messages
+    // since you can point out where problems are
+    pub fn history(&self) -> History<'_> {
+        let lex = self.lex.borrow();
+        History::new(lex.source(), lex.consumed().len(), lex.last())
+    }
+
+    // parse_identifier parses a name which can be written bare or wrapped,
+    // like the `FROM` clause values. Keywords must be wrapped.
+    pub fn parse_identifier(&self, wrap: &str) -> Result<String> {
+        let tok = must_token!(self)?;
+        if tok.kind() != TokenKind::Identifier || tok.wrap().is_some_and(|w| w != wrap) {
+            return Err(Error::with_history(
+                &format!("expected identifier but got \"{}\"", tok),
+                self.history(),
+            )
+            .hint(&format!(
+                "names which are keywords must be wrapped in {}",
+                wrap
+            )));
+        }
+
+        self.must_close(&tok)?;
+        self.unescape(tok.text())
+    }
+
+    // parse_duration parses a duration like `'10s'` or `'1 hour'`, the
+    // wrapping token is optional
+    pub fn parse_duration(&self, wrap: &str) -> Result<Duration> {
+        let tok = must_token!(self)?;
+        let valid = match tok.wrap() {
+            Some(w) => w == wrap,
+            None => matches!(tok.kind(), TokenKind::Number | TokenKind::Identifier),
+        };
+
+        self.must_close(&tok)?;
+        let value = if valid { tok.text() } else { "" };
+        parse_duration::parse(value).map_err(|err| {
+            Error::with_history(&format!("invalid duration {}", err), self.history())
+                .hint("durations are written like '10s' or '5 minutes'")
+        })
+    }
+
+    // unescape decodes the escape sequences in the text of the last token,
+    // pointing any error at the token
+    fn unescape(&self, text: &str) -> Result<String> {
+        unescape(text).map_err(|err| Error::with_history(&err.message(), self.history()))
+    }
+
+    // must_close returns an error when a wrapped token is missing the
+    // character that closes it
+    fn must_close(&self, tok: &Token<'_>) -> Result<()> {
+        match tok.wrap() {
+            Some(wrap) if !tok.closed() => Err(Error::with_history(
+                &format!("missing closing {}", wrap),
+                self.history(),
+            )
+            .hint(&format!("add a {} to the end of \"{}\"", wrap, tok.text()))),
+            _ => Ok(()),
+        }
     }
 
     // expression parses an expression, returning it as a Box<dyn Expression>
     pub fn expression(&self) -> Result<Expr> {
-        self.parse_expression_add()
+        self.nested(|| self.parse_expression_add())
     }
 
     // parse_expression_add makes it possible to support `Order Of Operations`.
     // This function handles adding and subtracting linearly, and passes lower
     // scopes into the multiply function
     fn parse_expression_add(&self) -> Result<Expr> {
-        let mut expr = self.parse_expression_multiply()?;
+        self.chain(|link| {
+            let mut expr = self.parse_expression_multiply()?;
 
-        loop {
-            let next = self.peak().unwrap_or_default();
-            match next {
-                ADD => {
-                    consume!(self);
-                    let right = self.parse_expression_multiply()?;
-                    expr = Expr::from(AddExpression::new(expr, right))
+            loop {
+                let next = self
+                    .peak()
+                    .filter(|v| v.kind() == TokenKind::Operator)
+                    .map(|v| v.text())
+                    .unwrap_or_default();
+                match next {
+                    ADD => {
+                        consume!(self);
+                        link()?;
+                        let right = self.parse_expression_multiply()?;
+                        expr = Expr::from(AddExpression::new(expr, right))
+                    }
+                    MINUS => {
+                        consume!(self);
+                        link()?;
+                        let right = self.parse_expression_multiply()?;
+                        expr = Expr::from(SubtractExpression::new(expr, right))
+                    }
+                    _ => break,
                 }
-                MINUS => {
-                    consume!(self);
-                    let right = self.parse_expression_multiply()?;
-                    expr = Expr::from(SubtractExpression::new(expr, right))
-                }
-                _ => break,
             }
-        }
 
-        Ok(expr)
+            Ok(expr)
+        })
     }
 
     // parse_expression_multiply makes it possible to support `Order Of Operations`.
     // This function handles multipling, dividing, remainder linearly, and passes lower
-    // scopes into the exponent function
+    // scopes into the unary function
     fn parse_expression_multiply(&self) -> Result<Expr> {
-        let mut expr = self.parse_expression_exponent()?;
+        self.chain(|link| {
+            let mut expr = self.parse_expression_unary()?;
 
-        loop {
-            let next = self.peak().unwrap_or_default();
-            match next {
-                MULTIPLY => {
-                    consume!(self);
-                    let right = self.parse_expression_exponent()?;
-                    expr = Expr::from(MultiplyExpression::new(expr, right))
-                }
-                DIVIDE => {
-                    consume!(self);
-                    let right = self.parse_expression_exponent()?;
-                    expr = Expr::from(DivideExpression::new(expr, right))
+            loop {
+                let next = self
+                    .peak()
+                    .filter(|v| v.kind() == TokenKind::Operator)
+                    .map(|v| v.text())
+                    .unwrap_or_default();
+                match next {
+                    MULTIPLY => {
+                        consume!(self);
+                        link()?;
+                        let right = self.parse_expression_unary()?;
+                        expr = Expr::from(MultiplyExpression::new(expr, right))
+                    }
+                    DIVIDE => {
+                        consume!(self);
+                        link()?;
+                        let right = self.parse_expression_unary()?;
+                        expr = Expr::from(DivideExpression::new(expr, right))
+                    }
+                    MODULUS => {
+                        consume!(self);
+                        link()?;
+                        let right = self.parse_expression_unary()?;
+                        expr = Expr::from(ModulusExpression::new(expr, right))
+                    }
+                    _ => break,
                 }
-                MODULUS => {
-                    consume!(self);
-                    let right = self.parse_expression_exponent()?;
-                    expr = Expr::from(ModulusExpression::new(expr, right))
-                }
-                _ => break,
             }
-        }
 
-        Ok(expr)
+            Ok(expr)
+        })
+    }
+
+    // parse_expression_unary handles the prefix operators -, + and NOT, which
+    // bind looser than exponents so `-a ^ 2` is `-(a ^ 2)`. Anything else is
+    // passed into the exponent function.
+    fn parse_expression_unary(&self) -> Result<Expr> {
+        let Some(tok) = self.peak() else {
+            return self.parse_expression_exponent();
+        };
+
+        let unary = |op: fn(Expr) -> Expr| {
+            consume!(self);
+            self.nested(|| self.parse_expression_unary()).map(op)
+        };
+
+        match tok.kind() {
+            TokenKind::Operator if tok.is(MINUS) => {
+                unary(|expr| Expr::from(NegativeExpression::new(expr)))
+            }
+            TokenKind::Operator if tok.is(ADD) => {
+                unary(|expr| Expr::from(PositiveExpression::new(expr)))
+            }
+            TokenKind::Operator | TokenKind::Keyword if tok.is(NEGATE) || tok.is(NOT) => {
+                unary(|expr| Expr::from(NotExpression::new(expr)))
+            }
+            _ => self.parse_expression_exponent(),
+        }
     }
 
     // parse_expression_exponent makes it possible to support `Order Of Operations`.
-    // This function handles exponents linearly, and passes execution into the
-    // parse_expression function
+    // Exponents are right associative, `a ^ b ^ c` is `a ^ (b ^ c)`, so the
+    // exponent is parsed by the unary function which passes back into this
+    // one. That also allows a unary exponent like `a ^ -b`.
     fn parse_expression_exponent(&self) -> Result<Expr> {
-        let mut expr = self.parse_expression()?;
+        let expr = self.parse_expression()?;
 
-        loop {
-            let next = self.peak().unwrap_or_default();
-            match next {
-                EXPONENT => {
-                    consume!(self);
-                    let right = self.parse_expression()?;
-                    expr = Expr::from(ExponentExpression::new(expr, right))
-                }
-                _ => break,
-            }
+        let exponent = self
+            .peak()
+            .filter(|v| v.kind() == TokenKind::Operator && v.is(EXPONENT));
+        if exponent.is_none() {
+            return Ok(expr);
         }
 
-        Ok(expr)
+        consume!(self);
+        let right = self.nested(|| self.parse_expression_unary())?;
+        Ok(Expr::from(ExponentExpression::new(expr, right)))
     }
 
     // parse_expression is used to parse expressions without evaluating math
     // in other words this handles all the things you would expect `expression`
     // to handle if you didn't have to deal with math.
     fn parse_expression(&self) -> Result<Expr> {
-        let left = self.peak().unwrap_or_default();
+        let Some(left) = self.peak() else {
+            return Err(Error::unexpected_eof(self.history()));
+        };
 
-        match left.to_uppercase().as_str() {
-            SUB_EXPR_OPEN => {
+        let name = left.text().to_uppercase();
+        match (left.kind(), left.wrap(), name.as_str()) {
+            (TokenKind::Punctuation, _, SUB_EXPR_OPEN) => {
                 consume!(self);
                 let expr = self.expression()?;
                 consume_next!(self, SUB_EXPR_CLOSE)?;
                 Ok(Expr::from(SubExpression::new(expr)))
             }
-            // KEY_WRAP => Ok(Box::new(PathExpression::from_parser(self)?)),
-            STRING_WRAP => Ok(Expr::from(self.string_literal()?)),
-            MAP_WRAP => Ok(Expr::from(self.map_literal()?)),
-            ARRAY_WRAP => Ok(Expr::from(self.list_literal()?)),
+            (TokenKind::Punctuation, _, MAP_WRAP) => Ok(Expr::from(self.map_literal()?)),
+            (TokenKind::Punctuation, _, ARRAY_WRAP) => Ok(Expr::from(self.list_literal()?)),
+            (TokenKind::String, _, _) => Ok(Expr::from(self.string_literal()?)),
+            (TokenKind::Number, _, _) => Ok(Expr::from(self.number_literal()?)),
+            (TokenKind::Keyword, _, TRUE | FALSE) => Ok(Expr::from(self.bool_literal()?)),
+            (TokenKind::Keyword, _, NULL) => Ok(Expr::from(self.null()?)),
+            (TokenKind::Parameter, _, _) | (TokenKind::Punctuation, _, PARAM_NAMED) => {
+                Ok(Expr::from(self.parameter_expression()?))
+            }
+            (TokenKind::Identifier, None, FN_TO_UPPER)
+                if self.is_function_call() && self.has_function(&name) =>
+            {
+                Ok(Expr::from(self.to_upper()?))
+            }
+            (
+                TokenKind::Identifier,
+                None,
+                AGGREGATION_SUM | AGGREGATION_COUNT | AGGREGATION_AVG,
+            ) if self.is_function_call() && self.has_function(&name) => {
+                Ok(Expr::from(self.aggregate_expression()?))
+            }
             // FN_LOWER => Ok(Box::new(StringLower::from_parser(self)?)),
-            // FN_UPPER => Ok(Box::new(StringUpper::from_parser(self)?)),
             // FN_LENGTH => Ok(Box::new(StringLength::from_parser(self)?)),
             // FN_TRIM => Ok(Box::new(StringTrim::from_parser(self)?)),
             // FN_TRIM_LEFT => Ok(Box::new(StringTrimLeft::from_parser(self)?)),
             // FN_TRIM_RIGHT => Ok(Box::new(StringTrimRight::from_parser(self)?)),
             // FN_CONCAT => Ok(Box::new(StringConcat::from_parser(self)?)),
             // FN_SPLIT => Ok(Box::new(StringSplit::from_parser(self)?)),
-            TRUE => Ok(Expr::from(self.bool_literal()?)),
-            FALSE => Ok(Expr::from(self.bool_literal()?)),
-            NULL => Ok(Expr::from(self.null()?)),
-            _ => self.parse_unwrapped_expression(),
+            (TokenKind::Identifier, None | Some(KEY_WRAP), _) => {
+                Ok(Expr::from(self.path_expression()?))
+            }
+            _ => Err(Error::with_history(
+                &format!("expected expression but got \"{}\"", must_token!(self)?),
+                self.history(),
+            )
+            .hint("expressions are paths, literals like 'text', 5 or true, and function calls")),
         }
     }
 
-    fn parse_unwrapped_expression(&self) -> Result<Expr> {
-        let mut chars = self.peak().unwrap_or_default().chars();
-        match chars.next() {
-            Some('0'..='9') | Some('-') => Ok(Expr::from(self.number_literal()?)),
-            // _ => Ok(Box::new(PathExpression::from_parser(self)?)),
-            _ => todo!(),
+    // is_function_call returns true when the next token is followed by
+    // FN_OPEN. This tells a call like `count(a)` apart from a path that
+    // happens to share its name, like `count`.
+    fn is_function_call(&self) -> bool {
+        let checkpoint = self.checkpoint();
+        consume!(self);
+        let call = is_next!(self, FN_OPEN);
+        self.restore(checkpoint);
+        call
+    }
+
+    // path_expression parses a path. Paths can be wrapped in KEY_WRAP, in
+    // which case any path is allowed, or they can be written bare as long as
+    // they don't contain whitespace or start with a wildcard.
+    fn path_expression(&self) -> Result<PathExpression> {
+        let tok = must_token!(self)?;
+        match (tok.kind(), tok.wrap()) {
+            (TokenKind::Identifier, Some(KEY_WRAP)) => {
+                self.must_close(&tok)?;
+                if tok.text().is_empty() {
+                    return Err(Error::with_history("empty path", self.history()));
+                }
+
+                let start = tok.range().start + KEY_WRAP.len();
+                return self.parse_path(tok.text(), start..start + tok.text().len());
+            }
+            (TokenKind::Identifier, None) => {
+                if is_next!(self, FN_OPEN) {
+                    return Err(Error::unknown_function(tok.text(), self.history()));
+                }
+            }
+            _ => {
+                return Err(Error::with_history(
+                    &format!("expected path but got \"{}\"", tok),
+                    self.history(),
+                ));
+            }
+        }
+
+        // bare paths are split into several tokens by the lexer, stitch
+        // together all the tokens that directly follow each other.
+        while self.adjacent() {
+            let dotted = self.lex.borrow().consumed().ends_with('.');
+            match self.peak() {
+                Some(next) if next.is(ARRAY_WRAP) => {
+                    consume!(self);
+                    must_token!(self)?;
+                    consume_next!(self, ARRAY_WRAP_END)?;
+                }
+                Some(next) if dotted && next.wrap() == Some(IDENTIFIER_WRAP) => {
+                    consume!(self);
+                    self.must_close(&next)?;
+                }
+                Some(next) if next.wrap().is_none() && (dotted || next.text().starts_with('.')) => {
+                    consume!(self);
+                }
+                _ => break,
+            }
+        }
+
+        let range = tok.range().start..self.lex.borrow().last().end;
+        let path = &self.lex.borrow().source()[range.clone()];
+        if !self.options.bare_paths {
+            return Err(Error::with_history(
+                &format!("expected wrapped path but got \"{}\"", path),
+                self.history(),
+            )
+            .hint(&format!(
+                "paths must be wrapped like {}{}{}",
+                KEY_WRAP, path, KEY_WRAP
+            )));
         }
+
+        self.parse_path(path, range)
+    }
+
+    // parse_path parses the path syntax, pointing any error at the range of
+    // the query the path was read from
+    fn parse_path(&self, path: &str, range: Range<usize>) -> Result<PathExpression> {
+        PathExpression::try_from(path).map_err(|err| match err {
+            Error::InvalidQuery(message) => Error::Parse {
+                message,
+                span: Span::new(self.lex.borrow().source(), range),
+                hint: None,
+            },
+            err => err,
+        })
     }
 
     // null parses and returns a null expression
@@ -331,22 +900,23 @@ impl<'a> Parser<'a> {
 
     // string_literal parses and returns a string literal
     fn string_literal(&self) -> Result<StringLiteral> {
-        consume_next!(self, STRING_WRAP)?;
-
-        // check for an empty string
-        if continue_if!(self, STRING_WRAP) {
-            return Ok(StringLiteral::new(String::new()));
+        let tok = must_token!(self)?;
+        if tok.kind() != TokenKind::String {
+            return Err(Error::with_history(
+                &format!("expected string but got \"{}\"", tok),
+                self.history(),
+            ));
         }
 
-        let value = must_token!(self)?;
-        consume_next!(self, STRING_WRAP)?;
-
-        Ok(StringLiteral::from(value))
+        self.must_close(&tok)?;
+        let value = self.unescape(tok.text())?;
+        self.must_literal_size("string", value.len())?;
+        Ok(StringLiteral::new(value))
     }
 
     // number_literal parses and returns a number literal
     fn number_literal(&self) -> Result<NumberLiteral> {
-        let tok = must_token!(self)?;
+        let tok = must_token!(self)?.text();
         let chars = tok.chars();
 
         if chars.filter(|c| *c == '.').count() == 1 {
@@ -354,9 +924,12 @@ impl<'a> Parser<'a> {
                 Error::with_history(&format!("expected float but got {}", e), self.history())
             })?;
 
+            Ok(NumberLiteral::from(num))
+        } else if let Ok(num) = tok.parse::<i64>() {
             Ok(NumberLiteral::from(num))
         } else {
-            let num = tok.parse::<i64>().map_err(|e| {
+            // integers too large for i64 can still fit in u64
+            let num = tok.parse::<u64>().map_err(|e| {
                 Error::with_history(&format!("expected integer but got {}", e), self.history())
             })?;
 
@@ -365,7 +938,7 @@ impl<'a> Parser<'a> {
     }
 
     fn bool_literal(&self) -> Result<BoolLiteral> {
-        let value = must_token!(self)?.to_uppercase();
+        let value = must_token!(self)?.text().to_uppercase();
 
         Ok(BoolLiteral::from(match value.as_str() {
             TRUE => true,
@@ -383,6 +956,10 @@ impl<'a> Parser<'a> {
         consume_next!(self, MAP_WRAP)?;
 
         let mut map = HashMap::new();
+        if continue_if!(self, MAP_WRAP_END) {
+            return Ok(MapLiteral::from(map));
+        }
+
         loop {
             // pase 'key': <expression>
             let key = self.string_literal()?;
@@ -390,10 +967,11 @@ impl<'a> Parser<'a> {
             let value = self.expression()?;
 
             map.insert(key.to_owned(), value);
+            self.must_literal_size("map", map.len())?;
 
             match must_token!(self)? {
-                MAP_WRAP_END => break,
-                MAP_CHILD_SEP => continue,
+                tok if tok.is(MAP_WRAP_END) => break,
+                tok if tok.is(MAP_CHILD_SEP) => continue,
                 tok => {
                     return Err(Error::with_history(
                         &format!("expected {MAP_CHILD_SEP} or {MAP_WRAP_END} but got {tok}"),
@@ -410,14 +988,19 @@ impl<'a> Parser<'a> {
         consume_next!(self, ARRAY_WRAP)?;
 
         let mut list = Vec::new();
+        if continue_if!(self, ARRAY_WRAP_END) {
+            return Ok(ListLiteral::from(list));
+        }
+
         loop {
             // pase 'key': <expression>
             let value = self.expression()?;
             list.push(value);
+            self.must_literal_size("list", list.len())?;
 
             match must_token!(self)? {
-                ARRAY_WRAP_END => break,
-                ARRAY_CHILD_SEP => continue,
+                tok if tok.is(ARRAY_WRAP_END) => break,
+                tok if tok.is(ARRAY_CHILD_SEP) => continue,
                 tok => {
                     return Err(Error::with_history(
                         &format!("expected {ARRAY_CHILD_SEP} or {ARRAY_WRAP_END} but got {tok}"),
@@ -430,3 +1013,177 @@ impl<'a> Parser<'a> {
         Ok(ListLiteral::from(list))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Any;
+
+    #[test]
+    fn test_parser_builder() -> Result<()> {
+        let strict = ParserBuilder::new().case_sensitive(true);
+        let query = strict
+            .build("SELECT select FROM events WHERE in IN [1] ORDER BY a NULLS LAST")
+            .query()?;
+        assert_eq!(
+            query.to_string(),
+            "SELECT `select` FROM events WHERE `in` IN [1] ORDER BY `a` ASC NULLS LAST"
+        );
+        assert!(strict.build("select a from events").query().is_err());
+        assert!(
+            strict
+                .build("SELECT a FROM events where a")
+                .query()
+                .is_err()
+        );
+        assert_eq!(
+            strict.build("a = true").condition()?.to_string(),
+            "`a` = `true`"
+        );
+        assert!(Parser::from("select a from events where a").query().is_ok());
+
+        let wrapped = ParserBuilder::new().bare_paths(false);
+        assert_eq!(
+            wrapped.build("`a.b` + 1").expression()?.to_string(),
+            "`a.b` + 1"
+        );
+        match wrapped.build("a.b + 1").expression().unwrap_err() {
+            Error::Parse { hint, .. } => {
+                assert_eq!(hint.as_deref(), Some("paths must be wrapped like `a.b`"))
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        let counting = ParserBuilder::new().functions(["count"]);
+        assert!(counting.build("COUNT(a) + count(*)").expression().is_ok());
+        assert!(matches!(
+            counting.build("SUM(a)").expression().unwrap_err(),
+            Error::UnknownFunction { .. }
+        ));
+        assert!(counting.build("to_upper('a')").expression().is_err());
+        assert!(counting.build("sum").expression().is_ok());
+
+        let shallow = ParserBuilder::new().max_depth(3);
+        assert!(shallow.build("((a))").expression().is_ok());
+        assert!(shallow.build("(((a)))").expression().is_err());
+        assert!(shallow.build("[[[1]]]").expression().is_err());
+        assert!(shallow.build("NOT a").condition().is_ok());
+        assert!(shallow.build("NOT NOT a").condition().is_err());
+
+        let single = ParserBuilder::new().double_equal(false);
+        assert!(single.build("a = 1").condition().is_ok());
+        assert!(single.build("a == 1").condition().is_err());
+        assert!(Parser::from("a == 1").condition().is_ok());
+
+        Ok(())
+    }
+
+    macro_rules! assert_limit {
+        ( $parsed:expr, $message:expr ) => {
+            match $parsed.unwrap_err() {
+                Error::LimitExceeded { message, .. } => assert_eq!(message, $message),
+                err => panic!("expected limit to be exceeded but got {:?}", err),
+            }
+        };
+    }
+
+    #[test]
+    fn test_parser_limits() -> Result<()> {
+        let deep = "(".repeat(10_000);
+        assert_limit!(
+            Parser::from(deep.as_str()).expression(),
+            "query is nested more than 64 levels deep"
+        );
+        assert_limit!(
+            Parser::from(format!("SELECT * FROM a WHERE {}", deep).as_str()).query(),
+            "query is nested more than 64 levels deep"
+        );
+        assert_limit!(
+            Parser::from("NOT ".repeat(10_000).as_str()).condition(),
+            "query is nested more than 64 levels deep"
+        );
+        let nested = format!("{}a{}", "(".repeat(60), ")".repeat(60));
+        assert!(Parser::from(nested.as_str()).expression().is_ok());
+
+        // flat chains are not nested, only their length is limited
+        let chain = vec!["1"; 1_000].join(" + ");
+        let expr = Parser::from(chain.as_str()).expression()?;
+        assert_eq!(expr.evaluate(&Any::Null)?, Any::from(1_000));
+        let chain = vec!["a = 1"; 1_000].join(" OR ");
+        assert!(Parser::from(chain.as_str()).condition().is_ok());
+        assert!(
+            Parser::from(format!("SELECT * FROM a WHERE {}", chain).as_str())
+                .query()
+                .is_ok()
+        );
+        let chain = vec!["a = 1"; 200_000].join(" AND ");
+        assert_limit!(
+            Parser::from(chain.as_str()).condition(),
+            "query has a chain of more than 1024 operators"
+        );
+
+        let chains = ParserBuilder::new().max_chain(2);
+        assert!(chains.build("a * b + c").expression().is_ok());
+        assert!(chains.build("(a + b) + c").expression().is_ok());
+        assert!(chains.build("(a + b) * (c - d)").expression().is_ok());
+        assert_limit!(
+            chains.build("((a + b) + c) + d").expression(),
+            "query has a chain of more than 2 operators"
+        );
+        assert_limit!(
+            chains.build("to_upper(a + b + c) + d").expression(),
+            "query has a chain of more than 2 operators"
+        );
+        assert_limit!(
+            chains.build("(a + b + c) + d").expression(),
+            "query has a chain of more than 2 operators"
+        );
+        assert_limit!(
+            chains.build("a OR b OR c OR d").condition(),
+            "query has a chain of more than 2 operators"
+        );
+
+        let tokens = ParserBuilder::new().max_tokens(5);
+        assert!(tokens.build("a + b + c").expression().is_ok());
+        assert_limit!(
+            tokens.build("a + b + c + d").expression(),
+            "query has more than 5 tokens"
+        );
+
+        let literals = ParserBuilder::new().max_literal_size(3);
+        assert!(literals.build("['abc', {'a': 1}]").expression().is_ok());
+        assert_limit!(
+            literals.build("'abcd'").expression(),
+            "string literal is larger than 3"
+        );
+        assert_limit!(
+            literals.build("[1, 2, 3, 4]").expression(),
+            "list literal is larger than 3"
+        );
+        assert_limit!(
+            literals
+                .build("{'a': 1, 'b': 2, 'c': 3, 'd': 4}")
+                .expression(),
+            "map literal is larger than 3"
+        );
+
+        let arguments = ParserBuilder::new().max_arguments(0);
+        assert!(
+            ParserBuilder::new()
+                .max_arguments(1)
+                .build("count(*)")
+                .expression()
+                .is_ok()
+        );
+        assert_limit!(
+            arguments.build("to_upper('a')").expression(),
+            "function has more than 0 arguments"
+        );
+        assert_limit!(
+            arguments.build("sum(a)").expression(),
+            "function has more than 0 arguments"
+        );
+
+        Ok(())
+    }
+}
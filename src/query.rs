@@ -0,0 +1,860 @@
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, fmt::Display, time::Duration};
+
+use crate::{
+    AggregateExpression, Any, Condition, Container, Error, Expr, Parser, Result, TokenKind,
+    lexor::escape,
+    parser::{
+        BY, EMIT, EVICT, FROM, FROM_SEP, GROUP, HAVING, IDENTIFIER_WRAP, INTERVAL, KEYWORDS, LIMIT,
+        MULTIPLY, OFFSET, ON, ORDER, ORDER_ASC, ORDER_DESC, ORDER_NULLS, ORDER_NULLS_FIRST,
+        ORDER_NULLS_LAST, SELECT, SELECT_ALIAS, SELECT_SEP, STRING_WRAP, WHERE, consume_next,
+        continue_if, must_token,
+    },
+};
+
+// Query is a fully parsed DQL statement. It holds every clause of the
+// statement, clauses that were not supplied are left empty.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    pub(crate) select: Vec<Projection>,
+    pub(crate) from: Vec<String>,
+    pub(crate) filter: Option<Condition>,
+    pub(crate) group: Vec<Expr>,
+    pub(crate) having: Option<Condition>,
+    pub(crate) interval: Option<Duration>,
+    pub(crate) evict: Option<Duration>,
+    pub(crate) emit: Option<Condition>,
+    pub(crate) order: Vec<OrderBy>,
+    pub(crate) limit: Option<usize>,
+    pub(crate) offset: Option<usize>,
+}
+
+impl Query {
+    // projections returns the projections listed in the `SELECT` clause
+    pub fn projections(&self) -> &[Projection] {
+        &self.select
+    }
+
+    // from returns the namespaces listed in the `FROM` clause
+    pub fn from(&self) -> &[String] {
+        &self.from
+    }
+
+    // filter returns the `WHERE` clause
+    pub fn filter(&self) -> Option<&Condition> {
+        self.filter.as_ref()
+    }
+
+    // group returns the expressions listed in the `GROUP BY` clause
+    pub fn group(&self) -> &[Expr] {
+        &self.group
+    }
+
+    // having returns the `HAVING` clause
+    pub fn having(&self) -> Option<&Condition> {
+        self.having.as_ref()
+    }
+
+    // interval returns the `INTERVAL` clause, how often a stream emits the
+    // current state of every group
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    // evict returns the `EVICT` clause, how long a stream keeps a group
+    // which has not been updated
+    pub fn evict(&self) -> Option<Duration> {
+        self.evict
+    }
+
+    // emit returns the `EMIT ON` clause, a stream emits a group as soon as
+    // it is updated and matches the condition
+    pub fn emit(&self) -> Option<&Condition> {
+        self.emit.as_ref()
+    }
+
+    // order returns the keys listed in the `ORDER BY` clause
+    pub fn order(&self) -> &[OrderBy] {
+        &self.order
+    }
+
+    // limit returns the `LIMIT` clause
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    // offset returns the `OFFSET` clause, the number of rows skipped before
+    // rows are returned
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    // matches returns true when the container passes the `WHERE` clause,
+    // queries without a `WHERE` clause match everything.
+    pub fn matches<T: Container>(&self, c: &T) -> Result<bool> {
+        match &self.filter {
+            Some(filter) => filter.evaluate(c),
+            None => Ok(true),
+        }
+    }
+
+    // matches_having returns true when the container passes the `HAVING`
+    // clause. For grouped queries the container is the group, which makes
+    // the aggregate results available to the condition.
+    pub fn matches_having<T: Container>(&self, c: &T) -> Result<bool> {
+        match &self.having {
+            Some(having) => having.evaluate(c),
+            None => Ok(true),
+        }
+    }
+
+    // aggregates returns every aggregate used by the query, these are the
+    // aggregates used by the `SELECT`, `HAVING`, `EMIT ON` and `ORDER BY`
+    // clauses
+    pub fn aggregates(&self) -> Vec<&AggregateExpression> {
+        let mut aggregates: Vec<_> = self
+            .select
+            .iter()
+            .flat_map(|projection| match projection {
+                Projection::Wildcard => Vec::new(),
+                Projection::Expr { expr, .. } => expr.aggregates(),
+            })
+            .collect();
+
+        for cond in self.having.iter().chain(self.emit.iter()) {
+            aggregates.extend(cond.aggregates());
+        }
+
+        for order in &self.order {
+            aggregates.extend(order.expr().aggregates());
+        }
+
+        aggregates
+    }
+}
+
+impl Display for Query {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ", SELECT)?;
+        write_list(f, &self.select, SELECT_SEP)?;
+
+        write!(f, " {} ", FROM)?;
+        for (i, ns) in self.from.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{} ", FROM_SEP)?;
+            }
+            write_identifier(f, ns)?;
+        }
+
+        if let Some(filter) = &self.filter {
+            write!(f, " {} {}", WHERE, filter)?;
+        }
+
+        if !self.group.is_empty() {
+            write!(f, " {} {} ", GROUP, BY)?;
+            write_list(f, &self.group, SELECT_SEP)?;
+        }
+
+        if let Some(having) = &self.having {
+            write!(f, " {} {}", HAVING, having)?;
+        }
+
+        if let Some(interval) = self.interval {
+            write!(f, " {} ", INTERVAL)?;
+            write_duration(f, interval)?;
+        }
+
+        if let Some(evict) = self.evict {
+            write!(f, " {} ", EVICT)?;
+            write_duration(f, evict)?;
+        }
+
+        if let Some(emit) = &self.emit {
+            write!(f, " {} {} {}", EMIT, ON, emit)?;
+        }
+
+        if !self.order.is_empty() {
+            write!(f, " {} {} ", ORDER, BY)?;
+            write_list(f, &self.order, SELECT_SEP)?;
+        }
+
+        if let Some(limit) = self.limit {
+            write!(f, " {} {}", LIMIT, limit)?;
+        }
+
+        if let Some(offset) = self.offset {
+            write!(f, " {} {}", OFFSET, offset)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Projection is a single entry in the `SELECT` clause
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Projection {
+    // Wildcard selects the whole document, `SELECT *`
+    Wildcard,
+    // Expr selects the result of an expression, optionally renamed
+    // with `AS`
+    Expr { expr: Expr, alias: Option<String> },
+}
+
+impl Projection {
+    // name returns the key the projection is stored under in a result,
+    // this is the alias when one was given, the path when a path is
+    // selected, otherwise the expression itself.
+    pub fn name(&self) -> String {
+        match self {
+            Projection::Wildcard => String::from(MULTIPLY),
+            Projection::Expr {
+                alias: Some(alias), ..
+            } => alias.clone(),
+            Projection::Expr {
+                expr: Expr::PathExpression(path),
+                ..
+            } => path.path(),
+            Projection::Expr { expr, .. } => expr.to_string(),
+        }
+    }
+}
+
+impl Display for Projection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Projection::Wildcard => write!(f, "{}", MULTIPLY),
+            Projection::Expr { expr, alias: None } => write!(f, "{}", expr),
+            Projection::Expr {
+                expr,
+                alias: Some(alias),
+            } => {
+                write!(f, "{} {} ", expr, SELECT_ALIAS)?;
+                write_identifier(f, alias)
+            }
+        }
+    }
+}
+
+// Direction is the direction an `ORDER BY` key is sorted in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Direction::Asc => write!(f, "{}", ORDER_ASC),
+            Direction::Desc => write!(f, "{}", ORDER_DESC),
+        }
+    }
+}
+
+// Nulls is where null values are placed by an `ORDER BY` key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Nulls {
+    First,
+    Last,
+}
+
+impl Display for Nulls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Nulls::First => write!(f, "{} {}", ORDER_NULLS, ORDER_NULLS_FIRST),
+            Nulls::Last => write!(f, "{} {}", ORDER_NULLS, ORDER_NULLS_LAST),
+        }
+    }
+}
+
+// OrderBy is a single key in the `ORDER BY` clause. When the placement of
+// nulls isn't given, nulls are treated as the smallest value, so they come
+// first when ascending and last when descending.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderBy {
+    expr: Expr,
+    direction: Direction,
+    nulls: Option<Nulls>,
+}
+
+impl OrderBy {
+    pub fn new(expr: Expr, direction: Direction, nulls: Option<Nulls>) -> Self {
+        OrderBy {
+            expr,
+            direction,
+            nulls,
+        }
+    }
+
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    pub(crate) fn expr_mut(&mut self) -> &mut Expr {
+        &mut self.expr
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    // nulls returns where nulls were asked to be placed, if at all
+    pub fn nulls(&self) -> Option<Nulls> {
+        self.nulls
+    }
+
+    // compare orders two values of this key, taking the direction and the
+    // placement of nulls into account
+    pub fn compare(&self, lhs: &Any<'_>, rhs: &Any<'_>) -> Ordering {
+        let nulls = self.nulls.unwrap_or(match self.direction {
+            Direction::Asc => Nulls::First,
+            Direction::Desc => Nulls::Last,
+        });
+
+        match (lhs, rhs, nulls) {
+            (Any::Null, Any::Null, _) => Ordering::Equal,
+            (Any::Null, _, Nulls::First) | (_, Any::Null, Nulls::Last) => Ordering::Less,
+            (Any::Null, _, Nulls::Last) | (_, Any::Null, Nulls::First) => Ordering::Greater,
+            _ => match self.direction {
+                Direction::Asc => lhs.total_cmp(rhs),
+                Direction::Desc => rhs.total_cmp(lhs),
+            },
+        }
+    }
+}
+
+impl Display for OrderBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.expr, self.direction)?;
+        if let Some(nulls) = self.nulls {
+            write!(f, " {}", nulls)?;
+        }
+
+        Ok(())
+    }
+}
+
+// write_list writes out each item separated by sep
+fn write_list<T: Display>(
+    f: &mut std::fmt::Formatter<'_>,
+    items: &[T],
+    sep: &str,
+) -> std::fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, "{} ", sep)?;
+        }
+        write!(f, "{}", item)?;
+    }
+
+    Ok(())
+}
+
+// write_identifier writes out an identifier, wrapping it when it can not
+// be read back unwrapped, like keywords or names starting with a digit.
+pub(crate) fn write_identifier(f: &mut std::fmt::Formatter<'_>, ident: &str) -> std::fmt::Result {
+    let bare = ident.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && ident.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(ident));
+
+    if bare {
+        write!(f, "{}", ident)
+    } else {
+        write!(
+            f,
+            "{}{}{}",
+            IDENTIFIER_WRAP,
+            escape(ident, IDENTIFIER_WRAP),
+            IDENTIFIER_WRAP
+        )
+    }
+}
+
+// write_duration writes out a duration in the largest unit that keeps it
+// exact, so it can be read back with Parser::parse_duration
+pub(crate) fn write_duration(
+    f: &mut std::fmt::Formatter<'_>,
+    duration: Duration,
+) -> std::fmt::Result {
+    let nanos = duration.as_nanos();
+    let (value, unit) = if nanos.is_multiple_of(1_000_000_000) {
+        (nanos / 1_000_000_000, "s")
+    } else if nanos.is_multiple_of(1_000_000) {
+        (nanos / 1_000_000, "ms")
+    } else if nanos.is_multiple_of(1_000) {
+        (nanos / 1_000, "us")
+    } else {
+        (nanos, "ns")
+    };
+
+    write!(f, "{}{}{}{}", STRING_WRAP, value, unit, STRING_WRAP)
+}
+
+impl<'a> TryFrom<&Parser<'a>> for Query {
+    type Error = crate::Error;
+    fn try_from(value: &Parser<'a>) -> std::result::Result<Self, Self::Error> {
+        value.query()
+    }
+}
+
+impl<'a> Parser<'a> {
+    // query parses an entire DQL statement. Every token must be consumed
+    // by the statement, anything left over is an error.
+    pub fn query(&self) -> Result<Query> {
+        let select = self.parse_select()?;
+        let from = self.parse_from()?;
+
+        let filter = if continue_if!(self, WHERE) {
+            self.recoverable(false, || self.condition())?
+        } else {
+            None
+        };
+
+        let group = self.parse_group()?;
+
+        let having = if continue_if!(self, HAVING) {
+            self.recoverable(false, || self.condition())?
+        } else {
+            None
+        };
+
+        let interval = if continue_if!(self, INTERVAL) {
+            self.recoverable(false, || self.parse_duration(STRING_WRAP))?
+        } else {
+            None
+        };
+
+        let evict = if continue_if!(self, EVICT) {
+            self.recoverable(false, || self.parse_duration(STRING_WRAP))?
+        } else {
+            None
+        };
+
+        let emit = if continue_if!(self, EMIT) {
+            self.recoverable(false, || {
+                consume_next!(self, ON)?;
+                self.condition()
+            })?
+        } else {
+            None
+        };
+
+        let order = self.parse_order()?;
+        let limit = self
+            .recoverable(false, || self.parse_count(LIMIT))?
+            .flatten();
+        let offset = self
+            .recoverable(false, || self.parse_count(OFFSET))?
+            .flatten();
+
+        while let Some(tok) = self.token() {
+            let err = Error::with_history(&format!("unexpected token \"{}\"", tok), self.history())
+                .hint(
+                    "clauses must be in the order SELECT, FROM, WHERE, GROUP BY, HAVING, \
+                     INTERVAL, EVICT, EMIT ON, ORDER BY, LIMIT, OFFSET",
+                );
+            self.recover(err, false)?;
+        }
+
+        Ok(Query {
+            select,
+            from,
+            filter,
+            group,
+            having,
+            interval,
+            evict,
+            emit,
+            order,
+            limit,
+            offset,
+        })
+    }
+
+    // query_diagnostics parses a query without stopping at the first error.
+    // Each error is recorded and parsing carries on from the next clause or
+    // separator, so a query with several mistakes reports all of them. The
+    // query returned holds whatever could be parsed, which is useful for
+    // editors that want to keep working with a query while it is typed.
+    pub fn query_diagnostics(&self) -> (Query, Vec<Error>) {
+        match self.diagnose(|| self.query()) {
+            (Ok(query), diagnostics) => (query, diagnostics),
+            (Err(err), mut diagnostics) => {
+                diagnostics.push(err);
+                (Query::default(), diagnostics)
+            }
+        }
+    }
+
+    // parse_list parses a list of comma separated items, when recovering an
+    // item which fails to parse is skipped
+    fn parse_list<T>(&self, item: impl Fn() -> Result<T>) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        loop {
+            if let Some(v) = self.recoverable(true, &item)? {
+                items.push(v);
+            }
+
+            if !continue_if!(self, SELECT_SEP) {
+                break;
+            }
+        }
+
+        Ok(items)
+    }
+
+    // parse_select parses the `SELECT` clause
+    fn parse_select(&self) -> Result<Vec<Projection>> {
+        if self
+            .recoverable(false, || consume_next!(self, SELECT))?
+            .is_none()
+        {
+            return Ok(Vec::new());
+        }
+
+        self.parse_list(|| self.parse_projection())
+    }
+
+    // parse_projection parses a single item of the `SELECT` clause
+    fn parse_projection(&self) -> Result<Projection> {
+        if continue_if!(self, MULTIPLY) {
+            return Ok(Projection::Wildcard);
+        }
+
+        let expr = self.expression()?;
+        let alias = if continue_if!(self, SELECT_ALIAS) {
+            Some(self.parse_identifier(IDENTIFIER_WRAP)?)
+        } else {
+            None
+        };
+
+        Ok(Projection::Expr { expr, alias })
+    }
+
+    // parse_from parses the `FROM` clause
+    fn parse_from(&self) -> Result<Vec<String>> {
+        if self
+            .recoverable(false, || consume_next!(self, FROM))?
+            .is_none()
+        {
+            return Ok(Vec::new());
+        }
+
+        self.parse_list(|| self.parse_identifier(IDENTIFIER_WRAP))
+    }
+
+    // parse_group parses the `GROUP BY` clause
+    fn parse_group(&self) -> Result<Vec<Expr>> {
+        if !continue_if!(self, GROUP)
+            || self
+                .recoverable(false, || consume_next!(self, BY))?
+                .is_none()
+        {
+            return Ok(Vec::new());
+        }
+
+        self.parse_list(|| self.expression())
+    }
+
+    // parse_order parses the `ORDER BY` clause
+    fn parse_order(&self) -> Result<Vec<OrderBy>> {
+        if !continue_if!(self, ORDER)
+            || self
+                .recoverable(false, || consume_next!(self, BY))?
+                .is_none()
+        {
+            return Ok(Vec::new());
+        }
+
+        self.parse_list(|| self.parse_order_by())
+    }
+
+    // parse_order_by parses a single item of the `ORDER BY` clause
+    fn parse_order_by(&self) -> Result<OrderBy> {
+        let expr = self.expression()?;
+        let direction = if continue_if!(self, ORDER_DESC) {
+            Direction::Desc
+        } else {
+            let _ = continue_if!(self, ORDER_ASC);
+            Direction::Asc
+        };
+
+        let nulls = if continue_if!(self, ORDER_NULLS) {
+            let tok = must_token!(self)?;
+            match tok {
+                tok if tok.is(ORDER_NULLS_FIRST) => Some(Nulls::First),
+                tok if tok.is(ORDER_NULLS_LAST) => Some(Nulls::Last),
+                _ => {
+                    return Err(Error::with_history(
+                        &format!(
+                            "expected \"{}\" or \"{}\" but got \"{}\"",
+                            ORDER_NULLS_FIRST, ORDER_NULLS_LAST, tok
+                        ),
+                        self.history(),
+                    ));
+                }
+            }
+        } else {
+            None
+        };
+
+        Ok(OrderBy::new(expr, direction, nulls))
+    }
+
+    // parse_count parses a clause which takes a number of rows, like the
+    // `LIMIT` and `OFFSET` clauses
+    fn parse_count(&self, clause: &str) -> Result<Option<usize>> {
+        if !continue_if!(self, clause) {
+            return Ok(None);
+        }
+
+        let tok = must_token!(self)?;
+        let count = tok
+            .text()
+            .parse::<usize>()
+            .ok()
+            .filter(|_| tok.kind() == TokenKind::Number)
+            .ok_or_else(|| {
+                Error::with_history(
+                    &format!("expected {} but got \"{}\"", clause.to_lowercase(), tok),
+                    self.history(),
+                )
+                .hint(&format!("{} takes a positive whole number", clause))
+            })?;
+
+        Ok(Some(count))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    macro_rules! assert_query {
+        ( $query:expr, $expected:expr ) => {
+            let parser = Parser::from($query);
+            let query = parser.query()?;
+            assert_eq!(query.to_string(), $expected);
+        };
+    }
+
+    #[test]
+    fn test_query() -> Result<()> {
+        assert_query!("SELECT * FROM events", "SELECT * FROM events");
+        assert_query!(
+            "select 'a' as three, to_upper('a') From events, \"other ns\"",
+            "SELECT 'a' AS three, TO_UPPER('a') FROM events, \"other ns\""
+        );
+        assert_query!(
+            "SELECT 'a' AS \"the key\" FROM events WHERE true GROUP BY 'a', 'b' HAVING false",
+            "SELECT 'a' AS \"the key\" FROM events WHERE TRUE GROUP BY 'a', 'b' HAVING FALSE"
+        );
+        assert_query!(
+            "SELECT a FROM events WHERE a = 'x' OR NOT (b != 'y' AND c) GROUP BY a HAVING a > 'b'",
+            "SELECT `a` FROM events WHERE `a` = 'x' OR NOT (`b` != 'y' AND `c`) GROUP BY `a` HAVING `a` > 'b'"
+        );
+        assert_query!(
+            "SELECT true FROM events ORDER BY 'a', 'b' desc, 'c' ASC LIMIT 10",
+            "SELECT TRUE FROM events ORDER BY 'a' ASC, 'b' DESC, 'c' ASC LIMIT 10"
+        );
+        assert_query!(
+            "SELECT true FROM events ORDER BY 'a' nulls first, 'b' DESC NULLS LAST",
+            "SELECT TRUE FROM events ORDER BY 'a' ASC NULLS FIRST, 'b' DESC NULLS LAST"
+        );
+        assert_query!(
+            "SELECT true FROM events LIMIT 10 offset 20",
+            "SELECT TRUE FROM events LIMIT 10 OFFSET 20"
+        );
+        assert_query!(
+            r#"SELECT 'it\'s\t"x"' AS "say \"hi\"" FROM events"#,
+            r#"SELECT 'it\'s\t"x"' AS "say \"hi\"" FROM events"#
+        );
+        assert_query!(
+            "-- count events\nSELECT count(*) /* everything */ AS total\nFROM events -- all of them",
+            "SELECT COUNT(*) AS total FROM events"
+        );
+        assert_query!(
+            "SELECT a FROM events WHERE a = $1 AND b IN :names LIMIT 10",
+            "SELECT `a` FROM events WHERE `a` = $1 AND `b` IN :names LIMIT 10"
+        );
+        assert_query!(
+            "SELECT `from` AS \"limit\", 'FROM' FROM \"order\"",
+            "SELECT `from` AS \"limit\", 'FROM' FROM \"order\""
+        );
+        assert_query!(
+            "SELECT a FROM events GROUP BY a INTERVAL '1m' evict '1500ms' EMIT ON a = 'x' LIMIT 10",
+            "SELECT `a` FROM events GROUP BY `a` INTERVAL '60s' EVICT '1500ms' EMIT ON `a` = 'x' LIMIT 10"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_clauses() -> Result<()> {
+        let parser = Parser::from("SELECT 10 AS ten, 'b' FROM a, b GROUP BY 1 LIMIT 3");
+        let query = parser.query()?;
+
+        assert_eq!(query.projections().len(), 2);
+        assert_eq!(query.projections()[0].name(), "ten");
+        assert_eq!(query.projections()[1].name(), "'b'");
+        assert_eq!(query.from(), &["a", "b"]);
+        assert!(query.filter().is_none());
+        assert_eq!(query.group().len(), 1);
+        assert!(query.having().is_none());
+        assert!(query.order().is_empty());
+        assert_eq!(query.limit(), Some(3));
+        assert!(query.offset().is_none());
+        assert!(query.interval().is_none());
+        assert!(query.evict().is_none());
+        assert!(query.emit().is_none());
+
+        let query = Parser::from("SELECT 1 FROM a INTERVAL 10s EVICT '2 hours'").query()?;
+        assert_eq!(query.interval(), Some(Duration::from_secs(10)));
+        assert_eq!(query.evict(), Some(Duration::from_secs(7200)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_matches() -> Result<()> {
+        let d: crate::Any = serde_json::from_str(r#"{"a": 5, "b": "x"}"#).unwrap();
+
+        let query = Parser::from("SELECT * FROM events WHERE a > 4 AND b = 'x'").query()?;
+        assert!(query.matches(&d)?);
+
+        let query = Parser::from("SELECT * FROM events WHERE a > 5 OR b = 'y'").query()?;
+        assert!(!query.matches(&d)?);
+
+        let query = Parser::from("SELECT * FROM events").query()?;
+        assert!(query.matches(&d)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_error_render() {
+        let source = "SELECT a\nFROM events\nWHERE a = 1\nLIMT 10";
+        let err = Parser::from(source).query().unwrap_err();
+        assert_eq!(err.span().map(|s| (s.line(), s.column())), Some((4, 1)));
+        assert_eq!(
+            err.render(source),
+            r#"error: unexpected token "LIMT"
+ --> 4:1
+  |
+4 | LIMT 10
+  | ^^^^
+  = hint: clauses must be in the order SELECT, FROM, WHERE, GROUP BY, HAVING, INTERVAL, EVICT, EMIT ON, ORDER BY, LIMIT, OFFSET"#
+        );
+
+        let source = "SELECT a\nFROM events LIMIT\n  ten";
+        let err = Parser::from(source).query().unwrap_err();
+        assert_eq!(err.span().map(|s| (s.line(), s.column())), Some((3, 3)));
+    }
+
+    #[test]
+    fn test_query_errors() {
+        assert!(Parser::from("SELECT 1").query().is_err());
+        assert!(Parser::from("FROM events").query().is_err());
+        assert!(
+            Parser::from("SELECT 1 FROM events LIMIT ten")
+                .query()
+                .is_err()
+        );
+        assert!(Parser::from("SELECT 1 FROM events 12").query().is_err());
+        assert!(
+            Parser::from("SELECT 1 FROM events LIMIT 1 OFFSET -1")
+                .query()
+                .is_err()
+        );
+        assert!(
+            Parser::from("SELECT 1 FROM events OFFSET 1 LIMIT 1")
+                .query()
+                .is_err()
+        );
+        assert!(
+            Parser::from("SELECT 1 FROM events INTERVAL 'soon'")
+                .query()
+                .is_err()
+        );
+        assert!(Parser::from("SELECT 1 FROM events EMIT a").query().is_err());
+        assert!(Parser::from("SELECT from FROM events").query().is_err());
+        assert!(Parser::from("SELECT $0 FROM events").query().is_err());
+        assert!(Parser::from("SELECT $a FROM events").query().is_err());
+        assert!(Parser::from("SELECT : name FROM events").query().is_err());
+        assert!(
+            Parser::from("SELECT a AS limit FROM events")
+                .query()
+                .is_err()
+        );
+        assert!(Parser::from("SELECT 'open FROM events").query().is_err());
+        assert!(Parser::from("SELECT `open FROM events").query().is_err());
+        assert!(
+            Parser::from("SELECT 1 FROM events LIMIT '5'")
+                .query()
+                .is_err()
+        );
+        assert!(
+            Parser::from("SELECT 1 FROM events ORDER BY a NULLS")
+                .query()
+                .is_err()
+        );
+        assert!(
+            Parser::from("SELECT 1 FROM events ORDER BY a NULLS NEVER")
+                .query()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_query_diagnostics() {
+        let parser = Parser::from("SELECT a +, b FROM events WHERE a = = 1 ORDER BY b LIMIT ten");
+        let (query, diagnostics) = parser.query_diagnostics();
+        let messages: Vec<_> = diagnostics.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "expected expression but got \",\" at 1:11",
+                "expected expression but got \"=\" at 1:37",
+                "expected limit but got \"ten\" at 1:58",
+            ]
+        );
+        assert_eq!(query.to_string(), "SELECT `b` FROM events ORDER BY `b` ASC");
+
+        // errors in a list only skip to the next item
+        let parser = Parser::from("SELECT a +, (b *), c FROM events GROUP BY a, b -, c");
+        let (query, diagnostics) = parser.query_diagnostics();
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(
+            query.to_string(),
+            "SELECT `c` FROM events GROUP BY `a`, `c`"
+        );
+
+        // clauses in the wrong order are each reported
+        let parser = Parser::from("SELECT a FROM events LIMIT 1 WHERE a OFFSET 1 GROUP BY a");
+        let (query, diagnostics) = parser.query_diagnostics();
+        let messages: Vec<_> = diagnostics.iter().map(|e| e.message()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "unexpected token \"WHERE\"",
+                "unexpected token \"OFFSET\"",
+                "unexpected token \"GROUP\"",
+            ]
+        );
+        assert_eq!(query.limit(), Some(1));
+        assert!(query.filter().is_none());
+
+        // a valid query has no diagnostics
+        let parser = Parser::from("SELECT a FROM events WHERE a > b");
+        let (query, diagnostics) = parser.query_diagnostics();
+        assert!(diagnostics.is_empty());
+        assert_eq!(query.to_string(), "SELECT `a` FROM events WHERE `a` > `b`");
+
+        // errors are still returned right away when not recovering
+        assert!(Parser::from("SELECT a +, b FROM events").query().is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 use serde::{
     Deserialize, Deserializer, Serialize,
@@ -6,7 +6,7 @@ use serde::{
     ser::{SerializeMap, SerializeSeq},
 };
 
-use crate::{Any, Bytes, Number, Str};
+use crate::{Any, Bytes, Condition, Expr, Number, OrderBy, Projection, Query, Str};
 
 macro_rules! impl_visitor {
     ($method:ident, $type:ty, $variant:ident) => {
@@ -30,6 +30,13 @@ impl<'de> Visitor<'de> for StrVisitor {
 
     impl_visitor!(visit_borrowed_str, &'de str, Str);
     impl_visitor!(visit_string, String, Str);
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(Str::from(String::from(v)))
+    }
 }
 
 struct AnyVisitor;
@@ -66,6 +73,13 @@ impl<'de> Visitor<'de> for AnyVisitor {
     impl_visitor!(visit_borrowed_str, &'de str, Any);
     impl_visitor!(visit_string, String, Any);
 
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(Any::from(String::from(v)))
+    }
+
     fn visit_none<E>(self) -> Result<Self::Value, E>
     where
         E: serde::de::Error,
@@ -176,7 +190,7 @@ impl Serialize for Bytes<'_> {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_bytes(self.as_ref())
+        serializer.serialize_bytes(self.as_slice())
     }
 }
 
@@ -192,3 +206,163 @@ impl Serialize for Number {
         }
     }
 }
+
+struct NumberVisitor;
+
+impl<'de> Visitor<'de> for NumberVisitor {
+    type Value = Number;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "expected number")
+    }
+
+    impl_visitor!(visit_i64, i64, Number);
+    impl_visitor!(visit_f64, f64, Number);
+
+    // integers are read as signed when they fit, like the parser reads
+    // number literals
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(i64::try_from(v).map_or(Number::UInteger(v), Number::Integer))
+    }
+}
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D>(deserializer: D) -> Result<Number, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(NumberVisitor)
+    }
+}
+
+// AST_VERSION is written alongside every serialized query. It changes
+// whenever the serialized form of the AST changes, so a query serialized by
+// a different version is rejected rather than misread.
+pub const AST_VERSION: u32 = 1;
+
+// QueryRef is the serialized form of a query, the clauses are written next
+// to the AST_VERSION
+#[derive(Serialize)]
+struct QueryRef<'a> {
+    version: u32,
+    select: &'a [Projection],
+    from: &'a [String],
+    filter: &'a Option<Condition>,
+    group: &'a [Expr],
+    having: &'a Option<Condition>,
+    interval: &'a Option<Duration>,
+    evict: &'a Option<Duration>,
+    emit: &'a Option<Condition>,
+    order: &'a [OrderBy],
+    limit: &'a Option<usize>,
+    offset: &'a Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct QueryOwned {
+    version: u32,
+    select: Vec<Projection>,
+    from: Vec<String>,
+    filter: Option<Condition>,
+    group: Vec<Expr>,
+    having: Option<Condition>,
+    interval: Option<Duration>,
+    evict: Option<Duration>,
+    emit: Option<Condition>,
+    order: Vec<OrderBy>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+impl Serialize for Query {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        QueryRef {
+            version: AST_VERSION,
+            select: &self.select,
+            from: &self.from,
+            filter: &self.filter,
+            group: &self.group,
+            having: &self.having,
+            interval: &self.interval,
+            evict: &self.evict,
+            emit: &self.emit,
+            order: &self.order,
+            limit: &self.limit,
+            offset: &self.offset,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Query {
+    fn deserialize<D>(deserializer: D) -> Result<Query, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let query = QueryOwned::deserialize(deserializer)?;
+        if query.version != AST_VERSION {
+            return Err(serde::de::Error::custom(format!(
+                "query was serialized with AST version {} but version {} is supported",
+                query.version, AST_VERSION
+            )));
+        }
+
+        Ok(Query {
+            select: query.select,
+            from: query.from,
+            filter: query.filter,
+            group: query.group,
+            having: query.having,
+            interval: query.interval,
+            evict: query.evict,
+            emit: query.emit,
+            order: query.order,
+            limit: query.limit,
+            offset: query.offset,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Parser, Result};
+
+    #[test]
+    fn test_query_serde() -> Result<()> {
+        let query = Parser::from(
+            "SELECT a AS b, COUNT(*), SUM(c.d[0] * 2.5), `~.*` FROM events, \"other ns\" \
+             WHERE (a = 'x' OR NOT b IN [1, $1]) AND :name GROUP BY a HAVING COUNT(*) > 1 \
+             INTERVAL '1m' EVICT '10s' EMIT ON TO_UPPER(a) = 'X' \
+             ORDER BY a DESC NULLS FIRST LIMIT 10 OFFSET 5",
+        )
+        .query()?;
+
+        let json = serde_json::to_string(&query).unwrap();
+        let decoded: Query = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", query));
+        assert_eq!(decoded.to_string(), query.to_string());
+
+        let expr = Parser::from("a + 1").expression()?;
+        assert_eq!(
+            serde_json::to_string(&expr).unwrap(),
+            r#"{"type":"add_expression","left":{"type":"path_expression","segments":[{"key":"a"}]},"right":{"type":"number_literal","value":1}}"#
+        );
+
+        let map = Parser::from("{'a': [NULL, TRUE]}").expression()?;
+        let decoded: Expr = serde_json::from_str(&serde_json::to_string(&map).unwrap()).unwrap();
+        assert_eq!(decoded.to_string(), map.to_string());
+
+        let json = json.replacen(&format!("\"version\":{}", AST_VERSION), "\"version\":0", 1);
+        let err = serde_json::from_str::<Query>(&json).unwrap_err();
+        assert!(err.to_string().contains("AST version 0"), "{}", err);
+
+        Ok(())
+    }
+}
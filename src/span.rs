@@ -0,0 +1,110 @@
+use std::{fmt::Display, ops::Range};
+
+// Span points at a part of a query. It holds the byte range within the query
+// along with the line and column the range starts on, both of which start
+// at 1. Columns count characters rather than bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    start: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Span {
+    // new creates a span for the byte range of source
+    pub fn new(source: &str, range: Range<usize>) -> Self {
+        let start = range.start.min(source.len());
+        let before = &source[..start];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or_default();
+
+        Span {
+            start,
+            end: range.end.clamp(start, source.len()),
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    // render writes out the line of source the span starts on, underlining
+    // the span with carets. Spans covering several lines are underlined to
+    // the end of the first line.
+    pub fn render(&self, source: &str) -> String {
+        let line = source.lines().nth(self.line - 1).unwrap_or_default();
+        let gutter = self.line.to_string();
+        let pad = " ".repeat(gutter.len());
+
+        let line_start = source[..self.start]
+            .rfind('\n')
+            .map(|i| i + 1)
+            .unwrap_or_default();
+        let line_end = line_start + line.len();
+        let width = source
+            .get(self.start..self.end.min(line_end))
+            .map(|s| s.chars().count())
+            .unwrap_or_default()
+            .max(1);
+
+        format!(
+            "{pad} |\n{gutter} | {line}\n{pad} | {}{}",
+            " ".repeat(self.column - 1),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_span() {
+        let source = "SELECT a\nFROM events\nWHERE é = 5";
+
+        let span = Span::new(source, 0..6);
+        assert_eq!((span.line(), span.column()), (1, 1));
+        assert_eq!(span.to_string(), "1:1");
+
+        let span = Span::new(source, 14..20);
+        assert_eq!((span.line(), span.column()), (2, 6));
+        assert_eq!(span.render(source), "  |\n2 | FROM events\n  |      ^^^^^^");
+
+        let span = Span::new(source, 30..31);
+        assert_eq!((span.line(), span.column()), (3, 9));
+        assert_eq!(span.render(source), "  |\n3 | WHERE é = 5\n  |         ^");
+
+        // spans at the end of the source still point somewhere
+        let span = Span::new(source, 33..33);
+        assert_eq!((span.line(), span.column()), (3, 12));
+        assert_eq!(
+            span.render(source),
+            "  |\n3 | WHERE é = 5\n  |            ^"
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use std::time::Instant;
+
+use crate::{Any, Bound, Container, Params, Query, Result, executor::Groups, executor::project};
+
+// Stream runs a query continuously over an unbounded number of containers.
+// Rather than producing rows once every container has been seen, rows are
+// emitted as the stream goes:
+//
+// - Queries that do not group containers emit a row for every matching
+//   container as soon as it is pushed.
+// - `EMIT ON <condition>` emits a group when an update makes the condition
+//   match the group. A group is not emitted again until an update makes the
+//   condition stop matching and then match again.
+// - `HAVING <condition>` filters every row before it is emitted.
+// - `ORDER BY` and `LIMIT` apply to the rows emitted by each tick.
+// - Rows emitted by push are counted for `OFFSET` and `LIMIT` over the
+//   lifetime of the stream, once the limit is reached push emits nothing.
+// - `INTERVAL '<duration>'` emits every group once the duration has passed
+//   since the last emit.
+// - `EVICT '<duration>'` drops groups which have not been updated within
+//   the duration.
+//
+// Groups keep aggregating between emits, they are only reset by being
+// evicted. Time is never read by the stream, the current time is passed to
+// push and tick by the caller.
+#[derive(Debug)]
+pub struct Stream<'q> {
+    query: &'q Query,
+    params: Option<&'q Params>,
+    groups: Option<Groups>,
+    emitted: Instant,
+    // pushed is how many rows push has emitted, including the rows skipped
+    // by `OFFSET`
+    pushed: usize,
+}
+
+impl<'q> Stream<'q> {
+    pub fn new(query: &'q Query, now: Instant) -> Self {
+        Stream {
+            query,
+            params: None,
+            groups: Groups::new(query),
+            emitted: now,
+            pushed: 0,
+        }
+    }
+
+    // with_params creates a stream which runs the query with the params
+    // bound to its parameters
+    pub fn with_params(query: &'q Query, params: &'q Params, now: Instant) -> Self {
+        Stream {
+            params: Some(params),
+            ..Stream::new(query, now)
+        }
+    }
+
+    // push evaluates a single container against the query, returning any
+    // rows which should be emitted right away
+    pub fn push<T: Container>(&mut self, c: &T, now: Instant) -> Result<Vec<Any<'static>>> {
+        let c = Bound::new(c, self.params);
+        if !self.query.matches(&c)? {
+            return Ok(Vec::new());
+        }
+
+        let Some(groups) = &mut self.groups else {
+            return emit_row(self.query, &mut self.pushed, &c);
+        };
+
+        let index = groups.update(self.query, &c)?;
+        groups.touch(index, now);
+
+        let Some(emit) = self.query.emit() else {
+            return Ok(Vec::new());
+        };
+
+        let matched = emit.evaluate(&Bound::new(groups.container(index), self.params))?;
+        if !groups.trigger(index, matched) {
+            return Ok(Vec::new());
+        }
+
+        let group = Bound::new(groups.container(index), self.params);
+        emit_row(self.query, &mut self.pushed, &group)
+    }
+
+    // tick moves the stream forward to now, returning every group when the
+    // interval has passed and evicting idle groups. Tick should be called
+    // regularly, at least as often as the interval.
+    pub fn tick(&mut self, now: Instant) -> Result<Vec<Any<'static>>> {
+        let Some(groups) = &mut self.groups else {
+            return Ok(Vec::new());
+        };
+
+        let mut rows = Vec::new();
+        if let Some(interval) = self.query.interval()
+            && now.saturating_duration_since(self.emitted) >= interval
+        {
+            rows = groups.rows(self.query, self.params)?;
+            self.emitted = now;
+        }
+
+        if let Some(evict) = self.query.evict()
+            && let Some(deadline) = now.checked_sub(evict)
+        {
+            groups.evict(deadline);
+        }
+
+        Ok(rows)
+    }
+}
+
+// emit_row projects a container pushed to the stream into a row, when it passes
+// the `HAVING` clause. pushed counts the rows emitted so far, so `OFFSET`
+// and `LIMIT` apply across every push rather than to each push.
+fn emit_row<T: Container>(query: &Query, pushed: &mut usize, c: &T) -> Result<Vec<Any<'static>>> {
+    let offset = query.offset().unwrap_or_default();
+    if query
+        .limit()
+        .is_some_and(|limit| *pushed >= offset.saturating_add(limit))
+    {
+        return Ok(Vec::new());
+    }
+
+    if !query.matches_having(c)? {
+        return Ok(Vec::new());
+    }
+
+    *pushed += 1;
+    if *pushed <= offset {
+        return Ok(Vec::new());
+    }
+
+    Ok(vec![project(query, c)?])
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::Parser;
+
+    macro_rules! any {
+        ( $json:expr ) => {
+            serde_json::from_str::<Any>($json).unwrap()
+        };
+    }
+
+    #[test]
+    fn test_stream_interval() -> Result<()> {
+        let query = Parser::from(
+            "SELECT host, COUNT(*) AS count FROM events GROUP BY host INTERVAL '10s' EVICT '30s'",
+        )
+        .query()?;
+
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut stream = Stream::new(&query, start);
+
+        assert!(stream.push(&any!(r#"{"host": "a"}"#), at(1))?.is_empty());
+        assert!(stream.push(&any!(r#"{"host": "b"}"#), at(2))?.is_empty());
+        assert!(stream.push(&any!(r#"{"host": "a"}"#), at(3))?.is_empty());
+        assert!(stream.tick(at(5))?.is_empty());
+
+        assert_eq!(
+            stream.tick(at(10))?,
+            vec![
+                any!(r#"{"host": "a", "count": 2}"#),
+                any!(r#"{"host": "b", "count": 1}"#)
+            ]
+        );
+        assert!(stream.tick(at(15))?.is_empty());
+
+        // groups keep aggregating between emits
+        stream.push(&any!(r#"{"host": "a"}"#), at(25))?;
+        assert_eq!(
+            stream.tick(at(25))?,
+            vec![
+                any!(r#"{"host": "a", "count": 3}"#),
+                any!(r#"{"host": "b", "count": 1}"#)
+            ]
+        );
+
+        // b was last updated at 2 seconds, so it is evicted after 32
+        stream.tick(at(33))?;
+        assert_eq!(
+            stream.tick(at(35))?,
+            vec![any!(r#"{"host": "a", "count": 3}"#)]
+        );
+
+        // evicted groups start over
+        stream.push(&any!(r#"{"host": "b"}"#), at(40))?;
+        assert_eq!(
+            stream.tick(at(45))?,
+            vec![
+                any!(r#"{"host": "a", "count": 3}"#),
+                any!(r#"{"host": "b", "count": 1}"#)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_emit() -> Result<()> {
+        let query = Parser::from(
+            "SELECT host, SUM(bytes) AS bytes FROM events WHERE bytes > 0 GROUP BY host EMIT ON SUM(bytes) >= 100",
+        )
+        .query()?;
+
+        let now = Instant::now();
+        let mut stream = Stream::new(&query, now);
+
+        assert!(
+            stream
+                .push(&any!(r#"{"host": "a", "bytes": 60}"#), now)?
+                .is_empty()
+        );
+        assert!(
+            stream
+                .push(&any!(r#"{"host": "b", "bytes": 10}"#), now)?
+                .is_empty()
+        );
+        assert!(
+            stream
+                .push(&any!(r#"{"host": "a", "bytes": 0}"#), now)?
+                .is_empty()
+        );
+        assert_eq!(
+            stream.push(&any!(r#"{"host": "a", "bytes": 40}"#), now)?,
+            vec![any!(r#"{"host": "a", "bytes": 100}"#)]
+        );
+        assert!(
+            stream
+                .push(&any!(r#"{"host": "a", "bytes": 10}"#), now)?
+                .is_empty()
+        );
+        assert!(stream.tick(now + Duration::from_secs(60))?.is_empty());
+
+        let query = Parser::from(
+            "SELECT host, COUNT(*) AS n FROM events GROUP BY host EMIT ON COUNT(*) % 2 = 0",
+        )
+        .query()?;
+        let mut stream = Stream::new(&query, now);
+        let mut push = || stream.push(&any!(r#"{"host": "a"}"#), now);
+        assert!(push()?.is_empty());
+        assert_eq!(push()?, vec![any!(r#"{"host": "a", "n": 2}"#)]);
+        assert!(push()?.is_empty());
+        assert_eq!(push()?, vec![any!(r#"{"host": "a", "n": 4}"#)]);
+
+        let query = Parser::from("SELECT host FROM events WHERE bytes > 0").query()?;
+        let mut stream = Stream::new(&query, now);
+        assert_eq!(
+            stream.push(&any!(r#"{"host": "a", "bytes": 60}"#), now)?,
+            vec![any!(r#"{"host": "a"}"#)]
+        );
+        assert!(
+            stream
+                .push(&any!(r#"{"host": "a", "bytes": 0}"#), now)?
+                .is_empty()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_limit() -> Result<()> {
+        let query = Parser::from("SELECT n FROM events WHERE n > 0 LIMIT 2 OFFSET 1").query()?;
+
+        let now = Instant::now();
+        let mut stream = Stream::new(&query, now);
+        let mut push = |n: i64| stream.push(&any!(&format!(r#"{{"n": {}}}"#, n)), now);
+
+        assert!(push(1)?.is_empty());
+        assert!(push(0)?.is_empty());
+        assert_eq!(push(2)?, vec![any!(r#"{"n": 2}"#)]);
+        assert_eq!(push(3)?, vec![any!(r#"{"n": 3}"#)]);
+        assert!(push(4)?.is_empty());
+        assert!(push(5)?.is_empty());
+
+        let query = Parser::from(
+            "SELECT host, COUNT(*) AS count FROM events GROUP BY host EMIT ON COUNT(*) >= 2 LIMIT 1 OFFSET 1",
+        )
+        .query()?;
+        let mut stream = Stream::new(&query, now);
+        let mut push = |host: &str| stream.push(&any!(&format!(r#"{{"host": "{}"}}"#, host)), now);
+
+        assert!(push("a")?.is_empty());
+        assert!(push("a")?.is_empty());
+        assert!(push("a")?.is_empty());
+        assert!(push("b")?.is_empty());
+        assert_eq!(push("b")?, vec![any!(r#"{"host": "b", "count": 2}"#)]);
+        assert!(push("c")?.is_empty());
+        assert!(push("c")?.is_empty());
+
+        Ok(())
+    }
+}
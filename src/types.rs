@@ -1,4 +1,10 @@
+use crate::lexor::escape;
+use crate::parser::{
+    ARRAY_CHILD_SEP, ARRAY_WRAP, ARRAY_WRAP_END, DIVIDE, FALSE, MAP_CHILD_SEP, MAP_CHILD_SET,
+    MAP_WRAP, MAP_WRAP_END, MINUS, NULL, STRING_WRAP, SUB_EXPR_CLOSE, SUB_EXPR_OPEN, TRUE,
+};
 use crate::{Container, Error};
+use std::borrow::Borrow;
 use std::cmp::Ordering;
 use std::hash::{DefaultHasher, Hash, Hasher};
 use std::ops::{Div, Mul, Rem, Sub};
@@ -6,8 +12,9 @@ use std::{collections::HashMap, fmt::Debug, fmt::Display};
 use std::{num::Wrapping, ops::Add};
 
 // Any defines all the data types that the query language can support
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum Any<'a> {
+    #[default]
     Null,
     Str(Str<'a>),
     Bytes(Bytes<'a>),
@@ -17,23 +24,212 @@ pub enum Any<'a> {
     Map(HashMap<Str<'a>, Any<'a>>),
 }
 
+// Kind is the type of an Any value, without the value itself. It makes it
+// possible to inspect the type of a value held within a container. Kinds are
+// ordered as they are declared, which is used to order values of different
+// kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kind {
+    Null,
+    Str,
+    Bytes,
+    Number,
+    Bool,
+    List,
+    Map,
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Kind::Null => "null",
+            Kind::Str => "string",
+            Kind::Bytes => "bytes",
+            Kind::Number => "number",
+            Kind::Bool => "bool",
+            Kind::List => "list",
+            Kind::Map => "map",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
 impl<'a> Any<'a> {
+    // kind returns the type of the value
+    pub fn kind(&self) -> Kind {
+        match self {
+            Any::Null => Kind::Null,
+            Any::Str(_) => Kind::Str,
+            Any::Bytes(_) => Kind::Bytes,
+            Any::Number(_) => Kind::Number,
+            Any::Bool(_) => Kind::Bool,
+            Any::List(_) => Kind::List,
+            Any::Map(_) => Kind::Map,
+        }
+    }
+
     pub fn as_str(&'a self) -> Result<&'a str, Error> {
         match self {
             Any::Str(v) => Ok(v.as_str()),
-            _ => Err(Error::InvalidType),
+            v => Err(Error::type_mismatch(Kind::Str, v.kind())),
         }
     }
 
     pub fn as_slice(&'a self) -> Result<&'a [u8], Error> {
         match self {
-            Any::Bytes(v) => Ok(v.as_ref()),
-            _ => Err(Error::InvalidType),
+            Any::Bytes(v) => Ok(v.as_slice()),
+            v => Err(Error::type_mismatch(Kind::Bytes, v.kind())),
+        }
+    }
+
+    // reborrow returns a copy of the value which references the strings and
+    // bytes held by self instead of copying them. Lists and maps still have
+    // to be rebuilt.
+    pub fn reborrow(&self) -> Any<'_> {
+        match self {
+            Any::Null => Any::Null,
+            Any::Str(v) => Any::Str(Str::Str(v.as_str())),
+            Any::Bytes(v) => Any::Bytes(Bytes::Ref(v.as_slice())),
+            Any::Number(v) => Any::Number(*v),
+            Any::Bool(v) => Any::Bool(*v),
+            Any::List(v) => Any::List(v.iter().map(Any::reborrow).collect()),
+            Any::Map(v) => Any::Map(
+                v.iter()
+                    .map(|(k, v)| (Str::Str(k.as_str()), v.reborrow()))
+                    .collect(),
+            ),
+        }
+    }
+
+    // into_owned copies any referenced strings and bytes so the value no
+    // longer borrows from anything.
+    pub fn into_owned(self) -> Any<'static> {
+        match self {
+            Any::Null => Any::Null,
+            Any::Str(v) => Any::Str(Str::String(v.as_string())),
+            Any::Bytes(v) => Any::Bytes(Bytes::Bytes(v.as_vec())),
+            Any::Number(v) => Any::Number(v),
+            Any::Bool(v) => Any::Bool(v),
+            Any::List(v) => Any::List(v.into_iter().map(Any::into_owned).collect()),
+            Any::Map(v) => Any::Map(
+                v.into_iter()
+                    .map(|(k, v)| (Str::String(k.as_string()), v.into_owned()))
+                    .collect(),
+            ),
+        }
+    }
+
+    // total_cmp orders any two values. Values that can be compared with
+    // PartialOrd are ordered that way, values of different kinds are ordered
+    // by their kind, NaN is greater than every other number, and lists and
+    // maps are compared item by item.
+    pub fn total_cmp(&self, other: &Any<'_>) -> Ordering {
+        match (self, other) {
+            (Any::Number(lhs), Any::Number(rhs)) => lhs
+                .partial_cmp(rhs)
+                .unwrap_or_else(|| is_nan(lhs).cmp(&is_nan(rhs))),
+            (Any::List(lhs), Any::List(rhs)) => lhs
+                .iter()
+                .zip(rhs)
+                .map(|(lhs, rhs)| lhs.total_cmp(rhs))
+                .find(|ord| ord.is_ne())
+                .unwrap_or_else(|| lhs.len().cmp(&rhs.len())),
+            (Any::Map(lhs), Any::Map(rhs)) => {
+                let mut lhs: Vec<_> = lhs.iter().collect();
+                let mut rhs: Vec<_> = rhs.iter().collect();
+                lhs.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+                rhs.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+
+                lhs.iter()
+                    .zip(&rhs)
+                    .map(|((lk, lv), (rk, rv))| {
+                        lk.as_str().cmp(rk.as_str()).then_with(|| lv.total_cmp(rv))
+                    })
+                    .find(|ord| ord.is_ne())
+                    .unwrap_or_else(|| lhs.len().cmp(&rhs.len()))
+            }
+            _ => self
+                .partial_cmp(other)
+                .unwrap_or_else(|| self.kind().cmp(&other.kind())),
+        }
+    }
+
+    // take_key consumes the value, returning the child stored under key
+    // when the value is a map.
+    pub fn take_key(self, key: &str) -> Option<Any<'a>> {
+        match self {
+            Any::Map(mut map) => map.remove(key),
+            _ => None,
+        }
+    }
+
+    // take_index consumes the value, returning the item stored at index
+    // when the value is a list.
+    pub fn take_index(self, index: usize) -> Option<Any<'a>> {
+        match self {
+            Any::List(list) => list.into_iter().nth(index),
+            _ => None,
+        }
+    }
+
+    // into_children consumes the value, returning each item of a list or
+    // each value of a map. Any other type has no children.
+    pub fn into_children(self) -> Vec<Any<'a>> {
+        match self {
+            Any::List(list) => list,
+            Any::Map(map) => map.into_values().collect(),
+            _ => Vec::new(),
         }
     }
 }
 
-impl Container for Any<'_> {}
+impl Container for Any<'_> {
+    fn kind(&self) -> Kind {
+        Any::kind(self)
+    }
+
+    fn value(&self) -> Any<'_> {
+        self.reborrow()
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Any::List(list) => list.len(),
+            Any::Map(map) => map.len(),
+            _ => 0,
+        }
+    }
+
+    fn keys(&self) -> Box<dyn Iterator<Item = Str<'_>> + '_> {
+        match self {
+            Any::Map(map) => Box::new(map.keys().map(|k| Str::Str(k.as_str()))),
+            _ => Box::new(std::iter::empty()),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<Any<'_>> {
+        match self {
+            Any::Map(map) => map.get(key).map(Any::reborrow),
+            _ => None,
+        }
+    }
+
+    fn index(&self, index: usize) -> Option<Any<'_>> {
+        match self {
+            Any::List(list) => list.get(index).map(Any::reborrow),
+            _ => None,
+        }
+    }
+
+    fn children(&self) -> Box<dyn Iterator<Item = Any<'_>> + '_> {
+        match self {
+            Any::List(list) => Box::new(list.iter().map(Any::reborrow)),
+            Any::Map(map) => Box::new(map.values().map(Any::reborrow)),
+            _ => Box::new(std::iter::empty()),
+        }
+    }
+}
 
 macro_rules! impl_any_from {
     ($type:ty, $variant:ident) => {
@@ -53,7 +249,7 @@ macro_rules! impl_any_try_from {
             fn try_from(value: Any<'a>) -> Result<Self, Self::Error> {
                 match value {
                     Any::$variant(v) => Ok(v.into()),
-                    _ => Err(Error::InvalidType),
+                    v => Err(Error::type_mismatch(Kind::$variant, v.kind())),
                 }
             }
         }
@@ -110,7 +306,7 @@ impl<'a> TryFrom<&'a Any<'a>> for &'a str {
     fn try_from(value: &'a Any<'a>) -> Result<Self, Self::Error> {
         match value {
             Any::Str(v) => Ok(v.into()),
-            _ => Err(Error::InvalidType),
+            v => Err(Error::type_mismatch(Kind::Str, v.kind())),
         }
     }
 }
@@ -121,7 +317,7 @@ impl<'a> TryFrom<&'a Any<'a>> for &'a [u8] {
     fn try_from(value: &'a Any<'a>) -> Result<Self, Self::Error> {
         match value {
             Any::Bytes(v) => Ok(v.into()),
-            _ => Err(Error::InvalidType),
+            v => Err(Error::type_mismatch(Kind::Bytes, v.kind())),
         }
     }
 }
@@ -138,17 +334,41 @@ impl<'a, const SIZE: usize> From<[(Str<'a>, Any<'a>); SIZE]> for Any<'a> {
     }
 }
 
-impl Default for Any<'_> {
-    fn default() -> Self {
-        Any::Null
-    }
-}
-
-// Display makes it possible to show the string value
+// Display writes the value as a DQL literal, so it can be parsed back into
+// an expression which returns the same value. Map keys are sorted so the
+// same map is always written the same way.
 impl Display for Any<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // TODO: This needs to be syntactically correct
-        Debug::fmt(self, f)
+        match self {
+            Any::Null => write!(f, "{}", NULL),
+            Any::Str(v) => write!(f, "{}", v),
+            Any::Bytes(v) => write!(f, "{}", v),
+            Any::Number(v) => write!(f, "{}", v),
+            Any::Bool(v) => write!(f, "{}", if *v { TRUE } else { FALSE }),
+            Any::List(list) => {
+                write!(f, "{}", ARRAY_WRAP)?;
+                for (i, v) in list.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "{} ", ARRAY_CHILD_SEP)?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                write!(f, "{}", ARRAY_WRAP_END)
+            }
+            Any::Map(map) => {
+                let mut entries = map.iter().collect::<Vec<_>>();
+                entries.sort_by(|(lhs, _), (rhs, _)| lhs.as_str().cmp(rhs.as_str()));
+
+                write!(f, "{}", MAP_WRAP)?;
+                for (i, (k, v)) in entries.into_iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "{} ", MAP_CHILD_SEP)?;
+                    }
+                    write!(f, "{}{} {}", k, MAP_CHILD_SET, v)?;
+                }
+                write!(f, "{}", MAP_WRAP_END)
+            }
+        }
     }
 }
 
@@ -185,10 +405,15 @@ impl PartialOrd for Any<'_> {
     }
 }
 
+// is_nan returns true when the number is a float that is NaN
+fn is_nan(num: &Number) -> bool {
+    matches!(num, Number::Float(f) if f.is_nan())
+}
+
 impl Hash for Any<'_> {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         match self {
-            Self::Null => state.write(&[0x0 as u8]),
+            Self::Null => state.write(&[0x0_u8]),
             Self::Str(str) => str.hash(state),
             Self::Bytes(b) => b.hash(state),
             Self::Number(num) => num.hash(state),
@@ -288,17 +513,30 @@ impl PartialOrd for Str<'_> {
     }
 }
 
-// Display makes it possible to show the string value
+// Display writes the string as a quoted and escaped string literal
 impl Display for Str<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // TODO: This needs to be syntactically correct
-        Debug::fmt(self, f)
+        write!(
+            f,
+            "{}{}{}",
+            STRING_WRAP,
+            escape(self.as_str(), STRING_WRAP),
+            STRING_WRAP
+        )
     }
 }
 
+// Hash must match the hash of str so that Borrow<str> can be used
+// to lookup map keys.
 impl Hash for Str<'_> {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        state.write(self.as_str().as_bytes());
+        self.as_str().hash(state);
+    }
+}
+
+impl Borrow<str> for Str<'_> {
+    fn borrow(&self) -> &str {
+        self.as_str()
     }
 }
 
@@ -345,14 +583,22 @@ impl_string_from!(&'a [u8], Ref);
 impl_string_from!(&'a Vec<u8>, Ref);
 
 impl<'a> Bytes<'a> {
-    // as_str returns a reference to the underlying string
-    pub fn as_ref(&self) -> &[u8] {
+    // as_slice returns a reference to the underlying bytes
+    pub fn as_slice(&self) -> &[u8] {
         match self {
             Self::Bytes(v) => v.as_ref(),
             Self::Ref(v) => v,
         }
     }
 
+    // as_ref is the old name of as_slice, kept so existing callers still
+    // build
+    #[deprecated(note = "use as_slice instead")]
+    #[allow(clippy::should_implement_trait)]
+    pub fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+
     // as string consumes the Bytes and returns a Vec, in the case
     // of an underlying &str it will copy the data and cause a heap
     // allocation.
@@ -367,7 +613,7 @@ impl<'a> Bytes<'a> {
 // PartialEq makes it possible to compare two strings
 impl PartialEq for Bytes<'_> {
     fn eq(&self, other: &Self) -> bool {
-        self.as_ref() == other.as_ref()
+        self.as_slice() == other.as_slice()
     }
 }
 
@@ -376,21 +622,21 @@ impl Eq for Bytes<'_> {}
 // PartialOrd implements >, <, >= and <=
 impl PartialOrd for Bytes<'_> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.as_ref().cmp(other.as_ref()))
+        Some(self.as_slice().cmp(other.as_slice()))
     }
 }
 
-// Display makes it possible to show the string value
+// Display shows the bytes, there is no literal for bytes so they can't be
+// parsed back
 impl Display for Bytes<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // TODO: This needs to be syntactically correct
         Debug::fmt(self, f)
     }
 }
 
 impl Hash for Bytes<'_> {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        state.write(self.as_ref());
+        state.write(self.as_slice());
     }
 }
 
@@ -442,6 +688,94 @@ impl_number_op!(Mul, mul, *);
 impl_number_op!(Div, div, /);
 impl_number_op!(Rem, rem, %);
 
+// checked operations return None instead of wrapping when an integer
+// overflows or is divided by zero. When unsigned integers can't hold the
+// result, like `3 - 5`, the operation is retried as signed integers.
+macro_rules! impl_number_checked_op {
+    ($fn:ident, $op:tt) => {
+        impl Number {
+            pub fn $fn(self, rhs: Self) -> Option<Number> {
+                let signed = |lhs: u64, rhs: u64| {
+                    i64::try_from(lhs)
+                        .ok()
+                        .zip(i64::try_from(rhs).ok())
+                        .and_then(|(lhs, rhs)| lhs.$fn(rhs))
+                };
+
+                match (self, rhs) {
+                    (Self::Float(lhs), Self::Float(rhs)) => Some(Number::Float(lhs $op rhs)),
+                    (Self::Float(lhs), Self::Integer(rhs)) => Some(Number::Float(lhs $op rhs as f64)),
+                    (Self::Float(lhs), Self::UInteger(rhs)) => Some(Number::Float(lhs $op rhs as f64)),
+                    (Self::Integer(lhs), Self::Float(rhs)) => Some(Number::Float(lhs as f64 $op rhs)),
+                    (Self::UInteger(lhs), Self::Float(rhs)) => Some(Number::Float(lhs as f64 $op rhs)),
+                    (Self::Integer(lhs), Self::Integer(rhs)) => lhs.$fn(rhs).map(Number::Integer),
+                    (Self::Integer(lhs), Self::UInteger(rhs)) => i64::try_from(rhs)
+                        .ok()
+                        .and_then(|rhs| lhs.$fn(rhs))
+                        .map(Number::Integer),
+                    (Self::UInteger(lhs), Self::Integer(rhs)) => i64::try_from(lhs)
+                        .ok()
+                        .and_then(|lhs| lhs.$fn(rhs))
+                        .map(Number::Integer),
+                    (Self::UInteger(lhs), Self::UInteger(rhs)) => lhs
+                        .$fn(rhs)
+                        .map(Number::UInteger)
+                        .or_else(|| signed(lhs, rhs).map(Number::Integer)),
+                }
+            }
+        }
+    };
+}
+
+impl_number_checked_op!(checked_add, +);
+impl_number_checked_op!(checked_sub, -);
+impl_number_checked_op!(checked_mul, *);
+impl_number_checked_op!(checked_div, /);
+impl_number_checked_op!(checked_rem, %);
+
+impl Number {
+    // checked_neg negates the number, returning None when an integer can't
+    // hold the result. Unsigned integers are negated into signed integers.
+    pub fn checked_neg(self) -> Option<Number> {
+        match self {
+            Self::Float(v) => Some(Number::Float(-v)),
+            Self::Integer(v) => v.checked_neg().map(Number::Integer),
+            Self::UInteger(v) => 0i64.checked_sub_unsigned(v).map(Number::Integer),
+        }
+    }
+
+    // checked_pow raises the number to the power of exp. Integers raised to
+    // a positive integer stay integers and return None when they overflow,
+    // anything else, like a float or a negative exponent, is raised as a
+    // float and returns None when the result is too large for a float.
+    pub fn checked_pow(self, exp: Number) -> Option<Number> {
+        let exp = match exp {
+            Self::Integer(v) if v >= 0 => v as u64,
+            Self::UInteger(v) => v,
+            _ => return self.checked_powf(exp),
+        };
+
+        // exponents too large for u32 overflow unless the number is 0, 1 or
+        // -1, the largest u32 with the same parity keeps the sign of -1
+        let exp = u32::try_from(exp).unwrap_or(u32::MAX - (exp % 2 == 0) as u32);
+        match self {
+            Self::Float(_) => self.checked_powf(Number::from(exp)),
+            Self::Integer(v) => v.checked_pow(exp).map(Number::Integer),
+            Self::UInteger(v) => v.checked_pow(exp).map(Number::UInteger),
+        }
+    }
+
+    fn checked_powf(self, exp: Number) -> Option<Number> {
+        let (base, exp) = (f64::from(self), f64::from(exp));
+        let result = base.powf(exp);
+        if result.is_infinite() && base.is_finite() && exp.is_finite() {
+            return None;
+        }
+
+        Some(Number::Float(result))
+    }
+}
+
 macro_rules! impl_number_from {
     ($type:ty, $variant:ident, $cast:ident) => {
         impl From<Number> for $type {
@@ -550,17 +884,44 @@ impl PartialOrd for Number {
     }
 }
 
-// Display makes it possible to show the string value
+// Display writes the number as a number literal. Floats always include a
+// decimal point so they are parsed back as floats, and are never written
+// with an exponent which the lexer wouldn't read as a single number. There
+// are no literals for NaN and infinity, so they are written as the division
+// which produces them, like `(1.0 / 0.0)`.
 impl Display for Number {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // TODO: This needs to be syntactically correct
-        Debug::fmt(self, f)
+        match self {
+            Self::Float(v) if !v.is_finite() => write!(
+                f,
+                "{}{}{} {} 0.0{}",
+                SUB_EXPR_OPEN,
+                if *v == f64::NEG_INFINITY { MINUS } else { "" },
+                if v.is_nan() { "0.0" } else { "1.0" },
+                DIVIDE,
+                SUB_EXPR_CLOSE
+            ),
+            Self::Float(v) if v.is_finite() && v.fract() == 0.0 => write!(f, "{}.0", v),
+            Self::Float(v) => write!(f, "{}", v),
+            Self::Integer(v) => write!(f, "{}", v),
+            Self::UInteger(v) => write!(f, "{}", v),
+        }
     }
 }
 
+// Hash agrees with PartialEq, floats with no fractional part hash the same
+// as the integer they are equal to. Every NaN hashes the same, so values
+// compared with total_cmp can be hashed as well.
 impl Hash for Number {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         match self {
+            Self::Float(f) if f.is_nan() => state.write_u64(f64::NAN.to_bits()),
+            Self::Float(f) if f.fract() == 0.0 && *f >= i64::MIN as f64 && *f < 0.0 => {
+                state.write_i64(*f as i64)
+            }
+            Self::Float(f) if f.fract() == 0.0 && *f >= 0.0 && *f < u64::MAX as f64 => {
+                state.write_u64(*f as u64)
+            }
             Self::Float(f) => state.write_u64(f.to_bits()),
             Self::Integer(i) => state.write_i64(*i),
             Self::UInteger(u) => state.write_u64(*u),
@@ -684,10 +1045,129 @@ mod tests {
         assert!(Number::UInteger(15) <= Number::UInteger(20));
     }
 
+    #[test]
+    fn test_container() {
+        let list = Any::from([Any::from(1), Any::from("a")]);
+        assert_eq!(Container::kind(&list), Kind::List);
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.index(1), Some(Any::from("a")));
+        assert_eq!(list.index(2), None);
+        assert_eq!(list.get("a"), None);
+        assert_eq!(list.children().count(), 2);
+        assert_eq!(list.keys().count(), 0);
+
+        let map = Any::from([(Str::from("a"), list.clone())]);
+        assert_eq!(Container::kind(&map), Kind::Map);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get("a"), Some(list.clone()));
+        assert_eq!(map.index(0), None);
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec![Str::from("a")]);
+        assert_eq!(map.value(), map);
+
+        let null = Any::Null;
+        assert_eq!(Container::kind(&null), Kind::Null);
+        assert!(null.is_empty());
+        assert_eq!(null.children().count(), 0);
+
+        assert_eq!(list.take_index(0), Some(Any::from(1)));
+        assert_eq!(map.take_key("b"), None);
+    }
+
+    #[test]
+    fn test_number_checked() {
+        let n = |n: u64| Number::UInteger(n);
+        assert!(matches!(n(3).checked_sub(n(5)), Some(Number::Integer(-2))));
+        assert!(matches!(n(3).checked_add(n(5)), Some(Number::UInteger(8))));
+        assert!(n(u64::MAX).checked_add(n(1)).is_none());
+        assert!(n(3).checked_div(n(0)).is_none());
+        assert!(Number::Integer(-3).checked_rem(n(0)).is_none());
+        assert!(
+            Number::Integer(i64::MIN)
+                .checked_mul(Number::Integer(-1))
+                .is_none()
+        );
+        assert!(matches!(
+            Number::Float(3.0).checked_div(n(0)),
+            Some(Number::Float(f)) if f.is_infinite()
+        ));
+    }
+
+    #[test]
+    fn test_total_cmp() {
+        let nan = Any::from(f64::NAN);
+        assert_eq!(nan.total_cmp(&nan), Ordering::Equal);
+        assert_eq!(nan.total_cmp(&Any::from(1)), Ordering::Greater);
+        assert_eq!(Any::from(1).total_cmp(&Any::from(1.5)), Ordering::Less);
+        assert_eq!(Any::Null.total_cmp(&Any::from("a")), Ordering::Less);
+        assert_eq!(Any::from("a").total_cmp(&Any::from(1)), Ordering::Less);
+        assert_eq!(Any::from(true).total_cmp(&Any::from(1)), Ordering::Greater);
+
+        let list = Any::from([Any::from(1), Any::from("a")]);
+        let other = Any::from([Any::from(1), Any::from(2)]);
+        assert_eq!(list.total_cmp(&other), Ordering::Less);
+        assert_eq!(list.total_cmp(&list), Ordering::Equal);
+
+        let map = Any::from([(Str::from("a"), Any::from(1))]);
+        let other = Any::from([(Str::from("a"), Any::from(2))]);
+        assert_eq!(map.total_cmp(&other), Ordering::Less);
+        assert_eq!(map.total_cmp(&list), Ordering::Greater);
+    }
+
     #[test]
     fn test_any() {
         assert_eq!(Any::Bool(true), Any::Bool(true));
         assert_ne!(Any::Number(Number::Float(23.5)), Any::Bool(true));
         assert_ne!(Any::Str(Str::Str("hello")), Any::Null);
     }
+
+    #[test]
+    fn test_any_display() {
+        let values = [
+            Any::Null,
+            Any::from("it's\n"),
+            Any::from(5),
+            Any::from(u64::MAX),
+            Any::from(2.0),
+            Any::from(0.5),
+            Any::from(1e20),
+            Any::from(f64::INFINITY),
+            Any::from(f64::NEG_INFINITY),
+            Any::from(true),
+            Any::List(Vec::new()),
+            Any::Map(HashMap::new()),
+            Any::from([
+                (Str::from("b"), Any::from([Any::from(1), Any::Null])),
+                (Str::from("a b"), Any::from("x")),
+            ]),
+        ];
+
+        for value in values {
+            let source = value.to_string();
+            let expr = crate::Parser::from(source.as_str()).expression().unwrap();
+            let result = expr.evaluate(&Any::Null).unwrap();
+            assert_eq!(result, value, "{}", value);
+            assert_eq!(result.kind(), value.kind(), "{}", value);
+        }
+
+        assert_eq!(Any::from(2.0).to_string(), "2.0");
+        assert_eq!(Any::from(f64::NEG_INFINITY).to_string(), "(-1.0 / 0.0)");
+
+        // NaN is never equal to itself, so it is checked on its own
+        let nan = Any::from(f64::NAN).to_string();
+        assert_eq!(nan, "(0.0 / 0.0)");
+        let expr = crate::Parser::from(nan.as_str()).expression().unwrap();
+        assert!(matches!(
+            expr.evaluate(&Any::Null).unwrap(),
+            Any::Number(Number::Float(v)) if v.is_nan()
+        ));
+        assert_eq!(Any::from("it's").to_string(), r"'it\'s'");
+        assert_eq!(
+            Any::from([
+                (Str::from("b"), Any::from([Any::from(1), Any::Null])),
+                (Str::from("a"), Any::from(false)),
+            ])
+            .to_string(),
+            "{'a': FALSE, 'b': [1, NULL]}"
+        );
+    }
 }
@@ -0,0 +1,344 @@
+use crate::{Condition, Expr, ExpressionCondition, NullExpression, OrderBy, Projection, Query};
+
+// Visitor walks a query, calling a method for every node it reaches. Each
+// method walks the children of its node by default, so a visitor only
+// overrides the nodes it is interested in and calls the matching walk
+// function to carry on into the children:
+//
+// struct Paths(Vec<String>);
+//
+// impl Visitor for Paths {
+//     fn visit_expr(&mut self, expr: &Expr) {
+//         if let Expr::PathExpression(path) = expr {
+//             self.0.push(path.path());
+//         }
+//         walk_expr(self, expr);
+//     }
+// }
+pub trait Visitor {
+    fn visit_query(&mut self, query: &Query) {
+        walk_query(self, query);
+    }
+
+    fn visit_projection(&mut self, projection: &Projection) {
+        walk_projection(self, projection);
+    }
+
+    fn visit_order_by(&mut self, order: &OrderBy) {
+        walk_order_by(self, order);
+    }
+
+    fn visit_condition(&mut self, cond: &Condition) {
+        walk_condition(self, cond);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+}
+
+// walk_query visits every clause of the query in the order they are written
+pub fn walk_query<V: Visitor + ?Sized>(v: &mut V, query: &Query) {
+    for projection in &query.select {
+        v.visit_projection(projection);
+    }
+
+    if let Some(filter) = &query.filter {
+        v.visit_condition(filter);
+    }
+
+    for expr in &query.group {
+        v.visit_expr(expr);
+    }
+
+    for cond in query.having.iter().chain(query.emit.iter()) {
+        v.visit_condition(cond);
+    }
+
+    for order in &query.order {
+        v.visit_order_by(order);
+    }
+}
+
+pub fn walk_projection<V: Visitor + ?Sized>(v: &mut V, projection: &Projection) {
+    if let Projection::Expr { expr, .. } = projection {
+        v.visit_expr(expr);
+    }
+}
+
+pub fn walk_order_by<V: Visitor + ?Sized>(v: &mut V, order: &OrderBy) {
+    v.visit_expr(order.expr());
+}
+
+pub fn walk_condition<V: Visitor + ?Sized>(v: &mut V, cond: &Condition) {
+    for cond in cond.conditions() {
+        v.visit_condition(cond);
+    }
+
+    for expr in cond.expressions() {
+        v.visit_expr(expr);
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(v: &mut V, expr: &Expr) {
+    for child in expr.children() {
+        v.visit_expr(child);
+    }
+}
+
+// MutVisitor is a Visitor which can change the nodes in place, like
+// renaming the paths of a query
+pub trait MutVisitor {
+    fn visit_query_mut(&mut self, query: &mut Query) {
+        walk_query_mut(self, query);
+    }
+
+    fn visit_projection_mut(&mut self, projection: &mut Projection) {
+        walk_projection_mut(self, projection);
+    }
+
+    fn visit_order_by_mut(&mut self, order: &mut OrderBy) {
+        walk_order_by_mut(self, order);
+    }
+
+    fn visit_condition_mut(&mut self, cond: &mut Condition) {
+        walk_condition_mut(self, cond);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+    }
+}
+
+pub fn walk_query_mut<V: MutVisitor + ?Sized>(v: &mut V, query: &mut Query) {
+    for projection in &mut query.select {
+        v.visit_projection_mut(projection);
+    }
+
+    if let Some(filter) = &mut query.filter {
+        v.visit_condition_mut(filter);
+    }
+
+    for expr in &mut query.group {
+        v.visit_expr_mut(expr);
+    }
+
+    for cond in query.having.iter_mut().chain(query.emit.iter_mut()) {
+        v.visit_condition_mut(cond);
+    }
+
+    for order in &mut query.order {
+        v.visit_order_by_mut(order);
+    }
+}
+
+pub fn walk_projection_mut<V: MutVisitor + ?Sized>(v: &mut V, projection: &mut Projection) {
+    if let Projection::Expr { expr, .. } = projection {
+        v.visit_expr_mut(expr);
+    }
+}
+
+pub fn walk_order_by_mut<V: MutVisitor + ?Sized>(v: &mut V, order: &mut OrderBy) {
+    v.visit_expr_mut(order.expr_mut());
+}
+
+pub fn walk_condition_mut<V: MutVisitor + ?Sized>(v: &mut V, cond: &mut Condition) {
+    for cond in cond.conditions_mut() {
+        v.visit_condition_mut(cond);
+    }
+
+    for expr in cond.expressions_mut() {
+        v.visit_expr_mut(expr);
+    }
+}
+
+pub fn walk_expr_mut<V: MutVisitor + ?Sized>(v: &mut V, expr: &mut Expr) {
+    for child in expr.children_mut() {
+        v.visit_expr_mut(child);
+    }
+}
+
+// Fold rebuilds a query by taking each node and returning its replacement,
+// which makes it possible to swap a node for one of a different kind, like
+// inlining the value of a parameter. Children are folded before the node
+// is returned.
+pub trait Fold {
+    fn fold_query(&mut self, query: Query) -> Query {
+        walk_query_fold(self, query)
+    }
+
+    fn fold_projection(&mut self, projection: Projection) -> Projection {
+        walk_projection_fold(self, projection)
+    }
+
+    fn fold_order_by(&mut self, order: OrderBy) -> OrderBy {
+        walk_order_by_fold(self, order)
+    }
+
+    fn fold_condition(&mut self, cond: Condition) -> Condition {
+        walk_condition_fold(self, cond)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        walk_expr_fold(self, expr)
+    }
+}
+
+pub fn walk_query_fold<F: Fold + ?Sized>(f: &mut F, mut query: Query) -> Query {
+    query.select = std::mem::take(&mut query.select)
+        .into_iter()
+        .map(|projection| f.fold_projection(projection))
+        .collect();
+    query.filter = query.filter.take().map(|cond| f.fold_condition(cond));
+    query.group = std::mem::take(&mut query.group)
+        .into_iter()
+        .map(|expr| f.fold_expr(expr))
+        .collect();
+    query.having = query.having.take().map(|cond| f.fold_condition(cond));
+    query.emit = query.emit.take().map(|cond| f.fold_condition(cond));
+    query.order = std::mem::take(&mut query.order)
+        .into_iter()
+        .map(|order| f.fold_order_by(order))
+        .collect();
+
+    query
+}
+
+pub fn walk_projection_fold<F: Fold + ?Sized>(f: &mut F, projection: Projection) -> Projection {
+    match projection {
+        Projection::Wildcard => Projection::Wildcard,
+        Projection::Expr { expr, alias } => Projection::Expr {
+            expr: f.fold_expr(expr),
+            alias,
+        },
+    }
+}
+
+pub fn walk_order_by_fold<F: Fold + ?Sized>(f: &mut F, mut order: OrderBy) -> OrderBy {
+    fold_in_place(order.expr_mut(), empty_expr, |expr| f.fold_expr(expr));
+    order
+}
+
+pub fn walk_condition_fold<F: Fold + ?Sized>(f: &mut F, mut cond: Condition) -> Condition {
+    for child in cond.conditions_mut() {
+        fold_in_place(child, empty_condition, |cond| f.fold_condition(cond));
+    }
+
+    for expr in cond.expressions_mut() {
+        fold_in_place(expr, empty_expr, |expr| f.fold_expr(expr));
+    }
+
+    cond
+}
+
+pub fn walk_expr_fold<F: Fold + ?Sized>(f: &mut F, mut expr: Expr) -> Expr {
+    for child in expr.children_mut() {
+        fold_in_place(child, empty_expr, |expr| f.fold_expr(expr));
+    }
+
+    expr
+}
+
+// fold_in_place folds a node which is only reachable through a reference,
+// the node is swapped for a placeholder while it is being folded
+fn fold_in_place<T>(node: &mut T, placeholder: fn() -> T, fold: impl FnOnce(T) -> T) {
+    let taken = std::mem::replace(node, placeholder());
+    *node = fold(taken);
+}
+
+fn empty_expr() -> Expr {
+    Expr::from(NullExpression::default())
+}
+
+fn empty_condition() -> Condition {
+    Condition::from(ExpressionCondition::new(empty_expr()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Conditional, NumberLiteral, Parameter, Parser, PathExpression, Result, Segment,
+        StringLiteral,
+    };
+
+    struct Paths(Vec<String>);
+
+    impl Visitor for Paths {
+        fn visit_expr(&mut self, expr: &Expr) {
+            if let Expr::PathExpression(path) = expr {
+                self.0.push(path.path());
+            }
+            walk_expr(self, expr);
+        }
+    }
+
+    struct Rename;
+
+    impl MutVisitor for Rename {
+        fn visit_expr_mut(&mut self, expr: &mut Expr) {
+            if let Expr::PathExpression(path) = expr
+                && path.path() == "a"
+            {
+                *expr = Expr::from(PathExpression::new(vec![Segment::Key(String::from("z"))]));
+            }
+            walk_expr_mut(self, expr);
+        }
+    }
+
+    struct Inline;
+
+    impl Fold for Inline {
+        fn fold_expr(&mut self, expr: Expr) -> Expr {
+            match expr {
+                Expr::ParameterExpression(param) => match param.parameter() {
+                    Parameter::Positional(index) => Expr::from(NumberLiteral::from(*index)),
+                    Parameter::Named(name) => Expr::from(StringLiteral::new(name.clone())),
+                },
+                expr => walk_expr_fold(self, expr),
+            }
+        }
+
+        fn fold_condition(&mut self, cond: Condition) -> Condition {
+            match cond {
+                Condition::SubCondition(sub) => {
+                    walk_condition_fold(self, sub.conditions()[0].clone())
+                }
+                cond => walk_condition_fold(self, cond),
+            }
+        }
+    }
+
+    #[test]
+    fn test_visitor() -> Result<()> {
+        let query = Parser::from(
+            "SELECT a, SUM(b + 1) AS total FROM events WHERE (c = $1 OR NOT d IN [e]) \
+             GROUP BY f HAVING COUNT(g) > 1 ORDER BY to_upper(h)",
+        )
+        .query()?;
+
+        let mut paths = Paths(Vec::new());
+        paths.visit_query(&query);
+        assert_eq!(paths.0, ["a", "b", "c", "d", "e", "f", "g", "h"]);
+
+        let mut renamed =
+            Parser::from("SELECT a FROM events WHERE a = b ORDER BY a + 1").query()?;
+        Rename.visit_query_mut(&mut renamed);
+        assert_eq!(
+            renamed.to_string(),
+            "SELECT `z` FROM events WHERE `z` = `b` ORDER BY `z` + 1 ASC"
+        );
+
+        let inlined = Inline.fold_query(query);
+        assert_eq!(
+            inlined.to_string(),
+            "SELECT `a`, SUM(`b` + 1) AS total FROM events WHERE `c` = 1 OR NOT `d` IN [`e`] \
+             GROUP BY `f` HAVING COUNT(`g`) > 1 ORDER BY TO_UPPER(`h`) ASC"
+        );
+
+        let cond = Parser::from(":name = [$2]").condition()?;
+        assert_eq!(Inline.fold_condition(cond).to_string(), "'name' = [2]");
+
+        Ok(())
+    }
+}